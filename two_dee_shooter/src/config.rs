@@ -0,0 +1,244 @@
+use ash::vk;
+
+// How much validation to run.
+// "Off" disables the validation layers and the debug messenger entirely.
+// "On" reports warnings and errors, while "Verbose" also reports info and verbose messages.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ValidationLevel {
+    Off,
+    On,
+    Verbose
+}
+
+// Validation is enabled for debug builds, and disabled for release builds, unless overridden.
+impl Default for ValidationLevel {
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            ValidationLevel::On
+        } else {
+            ValidationLevel::Off
+        }
+    }
+}
+
+impl ValidationLevel {
+    // The message severities the debug messenger reports at this level.
+    // VERBOSE and INFO messages flood the console, so they are only included at the verbose level.
+    pub fn message_severity(self) -> vk::DebugUtilsMessageSeverityFlagsEXT {
+        match self {
+            ValidationLevel::Off => vk::DebugUtilsMessageSeverityFlagsEXT::empty(),
+            ValidationLevel::On => vk::DebugUtilsMessageSeverityFlagsEXT::WARNING | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+            ValidationLevel::Verbose => {
+                vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
+                    | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
+                    | vk::DebugUtilsMessageSeverityFlagsEXT::INFO
+                    | vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE
+            }
+        }
+    }
+}
+
+// How the OS cursor behaves over the window.
+// "Hidden" hides the cursor while it is over the window, which lets the game draw its own crosshair instead.
+// "Disabled" hides and locks the cursor to the window, for unlimited mouse movement.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CursorMode {
+    Normal,
+    Hidden,
+    Disabled
+}
+
+// Where and how the main window is created.
+// "Fullscreen" takes exclusive ownership of the monitor, at its current video mode.
+// "Borderless" is GLFW's windowed fullscreen. It covers the whole monitor at its current video mode, but without a video mode switch,
+// which makes switching between it and other windows much faster.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WindowMode {
+    Windowed,
+    Fullscreen { monitor_index: usize },
+    Borderless { monitor_index: usize }
+}
+
+// Which present mode to ask for, when the surface supports it. FIFO is used otherwise, since it is the only mode guaranteed to be available.
+// "Mailbox" never tears and keeps latency low, at the cost of rendering frames that are never shown.
+// "FifoRelaxed" waits for vertical blank like FIFO, but presents a late frame immediately instead of waiting for the next one.
+// That avoids stutter when the frame rate briefly drops below the refresh rate, but the late frames may tear.
+// "Fifo" is classic vsync.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PresentModePreference {
+    Mailbox,
+    FifoRelaxed,
+    Fifo
+}
+
+// Optional device features the application needs.
+// A device that doesn't support all of them isn't suitable, and exactly these features are enabled on the logical device.
+#[derive(Default, Clone, Copy)]
+pub struct RequiredFeatures {
+    pub sampler_anisotropy: bool,
+    // Needed for non-fill polygon modes, such as wireframe rendering.
+    pub fill_mode_non_solid: bool,
+    // Needed for line widths other than 1.0.
+    pub wide_lines: bool,
+}
+
+// Runtime configuration of the application.
+// Everything starts out with a default value, which can be overridden through command line arguments.
+// Arguments are given either as "--name value" or "--name=value".
+pub struct Config {
+    // Index into the list of physical devices returned by Vulkan, to force a specific GPU on multi-GPU systems.
+    pub preferred_gpu_index: Option<usize>,
+    // Substring to match against physical device names, to force a specific GPU on multi-GPU systems.
+    pub preferred_gpu_name: Option<String>,
+    pub validation: ValidationLevel,
+    // How many queues to request from the graphics queue family. Capped at what the family supports.
+    pub desired_graphics_queue_count: u32,
+    // Caps the frame rate by sleeping at the end of each frame. Independent of the present mode. None means uncapped.
+    pub max_fps: Option<u32>,
+    // Closes the window after this many frames and prints frame time statistics, for profiling runs without any interaction.
+    pub frame_limit: Option<u32>,
+    // Prints the surface formats, present modes and capabilities of the selected device after surface creation.
+    pub list_surface_info: bool,
+    pub required_features: RequiredFeatures,
+    // Path to a PNG to use as the window icon. Without one, the window gets the default icon of the platform.
+    pub window_icon: Option<String>,
+    pub cursor_mode: CursorMode,
+    pub window_mode: WindowMode,
+    // Makes the window background see-through wherever the rendered alpha is below 1.0, for overlay-style builds.
+    // Requires both the window system and the surface to support blending the swapchain images with what's behind the window.
+    pub transparent_framebuffer: bool,
+    pub present_mode: PresentModePreference,
+    // Device extensions that a physical device must support to be selected, and which are enabled on the logical device.
+    // VK_KHR_swapchain is always included, since we can't present anything without it.
+    pub device_extensions: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            preferred_gpu_index: None,
+            preferred_gpu_name: None,
+            validation: ValidationLevel::default(),
+            desired_graphics_queue_count: 1,
+            max_fps: None,
+            frame_limit: None,
+            list_surface_info: false,
+            required_features: RequiredFeatures::default(),
+            window_icon: None,
+            cursor_mode: CursorMode::Normal,
+            window_mode: WindowMode::Windowed,
+            transparent_framebuffer: false,
+            present_mode: PresentModePreference::Mailbox,
+            device_extensions: vec!(String::from("VK_KHR_swapchain")),
+        }
+    }
+}
+
+impl Config {
+    // Besides the config, returns the arguments that weren't recognized.
+    // Parsing happens before the logger is set up, since the config decides its default verbosity, so the caller reports those.
+    // An invalid value for a known argument is an error, describing what's wrong with it.
+    pub fn from_args() -> Result<(Config, Vec<String>), String> {
+        let mut config = Config::default();
+        let mut ignored_arguments: Vec<String> = vec!();
+        let mut args = std::env::args().skip(1).peekable();
+
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None)
+            };
+
+            // Takes the value of the current argument, either from after the "=" or from the next argument.
+            let mut value = || {
+                inline_value.clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("Missing value for argument {}", name))
+            };
+
+            match name.as_str() {
+                "--gpu-index" => {
+                    let gpu_index = value()?;
+                    config.preferred_gpu_index = Some(gpu_index.parse().map_err(|_| format!("Invalid GPU index: {}", gpu_index))?);
+                },
+                "--gpu-name" => config.preferred_gpu_name = Some(value()?),
+                "--graphics-queues" => {
+                    let queue_count = value()?;
+                    config.desired_graphics_queue_count = queue_count.parse().map_err(|_| format!("Invalid graphics queue count: {}", queue_count))?;
+                },
+                "--max-fps" => {
+                    let max_fps = value()?;
+                    match max_fps.parse::<u32>() {
+                        Ok(fps) if fps > 0 => config.max_fps = Some(fps),
+                        _ => return Err(format!("Invalid max FPS: {}. Expected a positive number.", max_fps))
+                    }
+                },
+                "--frames" => {
+                    let frames = value()?;
+                    match frames.parse::<u32>() {
+                        Ok(frame_count) if frame_count > 0 => config.frame_limit = Some(frame_count),
+                        _ => return Err(format!("Invalid frame count: {}. Expected a positive number.", frames))
+                    }
+                },
+                "--list-surface-info" => config.list_surface_info = true,
+                "--require-feature" => {
+                    match value()?.as_str() {
+                        "sampler_anisotropy" => config.required_features.sampler_anisotropy = true,
+                        "fill_mode_non_solid" => config.required_features.fill_mode_non_solid = true,
+                        "wide_lines" => config.required_features.wide_lines = true,
+                        other => return Err(format!("Unknown device feature: {}", other))
+                    }
+                },
+                "--window-icon" => config.window_icon = Some(value()?),
+                "--cursor" => {
+                    config.cursor_mode = match value()?.as_str() {
+                        "normal" => CursorMode::Normal,
+                        "hidden" => CursorMode::Hidden,
+                        "disabled" => CursorMode::Disabled,
+                        other => return Err(format!("Invalid cursor mode: {}. Expected normal, hidden, or disabled.", other))
+                    };
+                },
+                // The monitor index is optional, and defaults to the primary monitor.
+                // So a following argument that is another flag, like "--fullscreen --validation=off", isn't taken as the index.
+                "--fullscreen" | "--borderless" => {
+                    let monitor_index = inline_value.clone()
+                        .or_else(|| args.next_if(|next_arg| !next_arg.starts_with("--")))
+                        .map(|monitor_index| monitor_index.parse().map_err(|_| format!("Invalid monitor index: {}", monitor_index)))
+                        .transpose()?
+                        .unwrap_or(0);
+                    config.window_mode = if name == "--fullscreen" {
+                        WindowMode::Fullscreen { monitor_index }
+                    } else {
+                        WindowMode::Borderless { monitor_index }
+                    };
+                },
+                "--transparent" => config.transparent_framebuffer = true,
+                "--present-mode" => {
+                    config.present_mode = match value()?.as_str() {
+                        "mailbox" => PresentModePreference::Mailbox,
+                        "fifo-relaxed" => PresentModePreference::FifoRelaxed,
+                        "fifo" => PresentModePreference::Fifo,
+                        other => return Err(format!("Invalid present mode: {}. Expected mailbox, fifo-relaxed, or fifo.", other))
+                    };
+                },
+                "--device-extension" => {
+                    let extension_name = value()?;
+                    if !config.device_extensions.contains(&extension_name) {
+                        config.device_extensions.push(extension_name);
+                    }
+                },
+                "--validation" => {
+                    config.validation = match value()?.as_str() {
+                        "off" => ValidationLevel::Off,
+                        "on" => ValidationLevel::On,
+                        "verbose" => ValidationLevel::Verbose,
+                        other => return Err(format!("Invalid validation level: {}. Expected off, on, or verbose.", other))
+                    };
+                },
+                _ => ignored_arguments.push(arg.clone())
+            }
+        }
+
+        Ok((config, ignored_arguments))
+    }
+}
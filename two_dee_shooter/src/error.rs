@@ -0,0 +1,107 @@
+use std::fmt;
+
+use ash::vk;
+
+// A reason for rejecting a physical device during device selection.
+#[derive(Debug)]
+pub enum UnsuitableReason {
+    NotDiscreteGpu,
+    NoGeometryShader,
+    NoGraphicsQueue,
+    NoPresentQueue,
+    MissingExtensions(Vec<String>),
+    InadequateSwapchain,
+    MissingFeatures(Vec<&'static str>),
+}
+
+impl fmt::Display for UnsuitableReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnsuitableReason::NotDiscreteGpu => write!(f, "not a discrete GPU"),
+            UnsuitableReason::NoGeometryShader => write!(f, "no geometry shader support"),
+            UnsuitableReason::NoGraphicsQueue => write!(f, "no graphics queue family"),
+            UnsuitableReason::NoPresentQueue => write!(f, "no queue family that can present to the window surface"),
+            UnsuitableReason::MissingExtensions(extension_names) => write!(f, "missing extensions {}", extension_names.join(", ")),
+            UnsuitableReason::InadequateSwapchain => write!(f, "no surface formats or present modes for the window surface"),
+            UnsuitableReason::MissingFeatures(feature_names) => write!(f, "missing features {}", feature_names.join(", ")),
+        }
+    }
+}
+
+// Errors that can happen while setting up the renderer.
+// Each variant names the stage that failed, so the error tells you where initialization stopped,
+// and wraps the Vulkan result code that the stage failed with.
+#[derive(Debug)]
+pub enum RendererError {
+    Instance(vk::Result),
+    MissingValidationLayer(String),
+    MissingInstanceExtensions(Vec<String>),
+    DebugMessenger(vk::Result),
+    Surface(vk::Result),
+    // Querying what the surface supports, as opposed to creating it.
+    SurfaceQuery(vk::Result),
+    PresentNotSupported(u32),
+    PhysicalDevice(vk::Result),
+    DeviceExtensionQuery(vk::Result),
+    NoVulkanDevices,
+    MissingDeviceFeatures(Vec<&'static str>),
+    // Lists every physical device by name, together with the reasons it was rejected.
+    NoSuitableDevice(Vec<(String, Vec<UnsuitableReason>)>),
+    Device(vk::Result),
+    NoSurfaceFormats,
+    Swapchain(vk::Result),
+    SwapchainImages(vk::Result),
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RendererError::Instance(result) => write!(f, "Failed to create Vulkan instance: {}", result),
+            RendererError::MissingValidationLayer(layer_name) => write!(f, "The required validation layer {} could not be found in the list of available layers.", layer_name),
+            RendererError::MissingInstanceExtensions(extension_names) => write!(f, "The required instance extensions are not available: {}", extension_names.join(", ")),
+            RendererError::DebugMessenger(result) => write!(f, "Failed to create debug utils messenger: {}", result),
+            RendererError::Surface(result) => write!(f, "Failed to create window surface: {}", result),
+            RendererError::SurfaceQuery(result) => write!(f, "Failed to query window surface support: {}", result),
+            RendererError::PresentNotSupported(family_index) => write!(f, "Queue family {} of the selected device does not support presenting to the window surface.", family_index),
+            RendererError::PhysicalDevice(result) => write!(f, "Failed to retrieve physical devices: {}", result),
+            RendererError::DeviceExtensionQuery(result) => write!(f, "Failed to query device extensions: {}", result),
+            RendererError::NoVulkanDevices => write!(f, "No Vulkan-capable GPUs found — is a driver installed?"),
+            RendererError::MissingDeviceFeatures(feature_names) => write!(f, "No physical device supports the required features: {}", feature_names.join(", ")),
+            RendererError::NoSuitableDevice(rejected_devices) => {
+                write!(f, "Failed to select a physical device:")?;
+                for (device_name, reasons) in rejected_devices {
+                    let reasons: Vec<String> = reasons.iter().map(|reason| reason.to_string()).collect();
+                    write!(f, "\n    {}: {}", device_name, reasons.join(", "))?;
+                }
+                Ok(())
+            },
+            RendererError::Device(result) => write!(f, "Failed to create logical device: {}", result),
+            RendererError::NoSurfaceFormats => write!(f, "The window surface reports no supported formats."),
+            RendererError::Swapchain(result) => write!(f, "Failed to create swapchain: {}", result),
+            RendererError::SwapchainImages(result) => write!(f, "Failed to retrieve swapchain images: {}", result),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RendererError::Instance(result)
+            | RendererError::DebugMessenger(result)
+            | RendererError::Surface(result)
+            | RendererError::SurfaceQuery(result)
+            | RendererError::PhysicalDevice(result)
+            | RendererError::DeviceExtensionQuery(result)
+            | RendererError::Device(result)
+            | RendererError::Swapchain(result)
+            | RendererError::SwapchainImages(result) => Some(result),
+            RendererError::MissingValidationLayer(_)
+            | RendererError::MissingInstanceExtensions(_)
+            | RendererError::PresentNotSupported(_)
+            | RendererError::NoVulkanDevices
+            | RendererError::MissingDeviceFeatures(_)
+            | RendererError::NoSuitableDevice(_)
+            | RendererError::NoSurfaceFormats => None,
+        }
+    }
+}
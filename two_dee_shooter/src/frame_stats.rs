@@ -0,0 +1,49 @@
+use std::fmt;
+
+// Aggregated frame times over a run, in seconds.
+// Only the totals are kept, so recording a frame is cheap no matter how long the run is.
+pub struct FrameStats {
+    frame_count: u32,
+    total_time: f64,
+    min_time: f64,
+    max_time: f64
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        FrameStats {
+            frame_count: 0,
+            total_time: 0.0,
+            min_time: f64::MAX,
+            max_time: 0.0
+        }
+    }
+
+    pub fn record(&mut self, frame_time: f64) {
+        self.frame_count += 1;
+        self.total_time += frame_time;
+        self.min_time = self.min_time.min(frame_time);
+        self.max_time = self.max_time.max(frame_time);
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.frame_count == 0 {
+            return write!(f, "No frames were run.");
+        }
+
+        write!(
+            f,
+            "Ran {} frames in {:.3} s. Frame time avg {:.3} ms, min {:.3} ms, max {:.3} ms.",
+            self.frame_count,
+            self.total_time,
+            self.total_time / self.frame_count as f64 * 1000.0,
+            self.min_time * 1000.0,
+            self.max_time * 1000.0)
+    }
+}
@@ -0,0 +1,56 @@
+use std::ffi::CStr;
+use std::fmt;
+
+use ash::vk;
+
+// A summary of the selected physical device, meant for displaying to the user, for example in a diagnostics overlay.
+// It is captured once after device selection, so reading it later doesn't have to query Vulkan again.
+pub struct GpuInfo {
+    pub name: String,
+    pub device_type: String,
+    pub api_version: (u32, u32, u32),
+    // The driver version encoding is vendor specific, so it's kept as the raw value.
+    pub driver_version: u32,
+    // The largest width and height of a 2D image (and thereby texture) the device supports.
+    pub max_texture_size: u32,
+}
+
+// Device names come from the driver, and aren't guaranteed to be valid UTF-8.
+pub unsafe fn get_device_name(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> String {
+    let device_properties = instance.get_physical_device_properties(physical_device);
+    CStr::from_ptr(device_properties.device_name.as_ptr()).to_string_lossy().into_owned()
+}
+
+impl GpuInfo {
+    pub unsafe fn capture(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> GpuInfo {
+        let device_properties = instance.get_physical_device_properties(physical_device);
+
+        let device_type = match device_properties.device_type {
+            vk::PhysicalDeviceType::DISCRETE_GPU => "Discrete GPU",
+            vk::PhysicalDeviceType::INTEGRATED_GPU => "Integrated GPU",
+            vk::PhysicalDeviceType::VIRTUAL_GPU => "Virtual GPU",
+            vk::PhysicalDeviceType::CPU => "CPU",
+            _ => "Other"
+        };
+
+        GpuInfo {
+            name: get_device_name(instance, physical_device),
+            device_type: device_type.to_string(),
+            api_version: (
+                vk::api_version_major(device_properties.api_version),
+                vk::api_version_minor(device_properties.api_version),
+                vk::api_version_patch(device_properties.api_version)
+            ),
+            driver_version: device_properties.driver_version,
+            max_texture_size: device_properties.limits.max_image_dimension2_d,
+        }
+    }
+}
+
+impl fmt::Display for GpuInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (major, minor, patch) = self.api_version;
+        write!(f, "{} ({}), Vulkan {}.{}.{}, driver version {}, max texture size {}",
+            self.name, self.device_type, major, minor, patch, self.driver_version, self.max_texture_size)
+    }
+}
@@ -1,7 +1,10 @@
 use std::borrow::Borrow;
 use std::collections::HashSet;
 use std::ffi::{ CString, CStr, c_void };
+use std::fs::File;
 use std::ptr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use ash::vk::{Handle, DeviceQueueCreateFlags};
 use ash::{vk, Entry};
@@ -9,139 +12,111 @@ use ash::{vk, Entry};
 use beagle_glfw::*;
 
 #[macro_use]
-extern crate lazy_static;
+extern crate log;
+
+mod config;
+mod error;
+mod frame_stats;
+mod gpu_info;
+mod validation_stats;
+mod vulkan_context;
+
+use config::{Config, CursorMode, PresentModePreference, RequiredFeatures, ValidationLevel, WindowMode};
+use error::{RendererError, UnsuitableReason};
+use frame_stats::FrameStats;
+use gpu_info::{get_device_name, GpuInfo};
+use validation_stats::ValidationStats;
+use vulkan_context::{DebugMessengerGuard, DeviceGuard, GlfwGuard, InstanceGuard, SurfaceGuard, SwapchainGuard, ValidationStatsGuard, VulkanContext, WindowGuard};
 
 static WIDTH: i32 = 800;
 static HEIGHT: i32 = 600;
 
-lazy_static! {
-    static ref REQUIRED_EXTENSIONS: HashSet<String> = {
-        let mut m = HashSet::new();
-        m.insert(String::from("VK_KHR_swapchain"));
-        m
+fn main() {
+    let (config, ignored_arguments) = match Config::from_args() {
+        Ok(parsed_arguments) => parsed_arguments,
+        Err(err) => {
+            // Without a valid config there's no validation level to go by, so the logger gets its usual default, just to report the problem.
+            init_logger("info");
+            error!("{}", err);
+            std::process::exit(1);
+        }
     };
+
+    // Verbose validation reports its VERBOSE messages at debug level, so in that case debug level is shown by default as well.
+    init_logger(if config.validation == ValidationLevel::Verbose { "debug" } else { "info" });
+
+    for ignored_argument in ignored_arguments {
+        warn!("Ignoring unknown argument: {}", ignored_argument);
+    }
+
+    if let Err(err) = run(&config) {
+        error!("{}", err);
+        std::process::exit(1);
+    }
 }
 
-static mut VK_ENTRY: Option<ash::Entry> = None;
-static mut VK_INSTANCE: Option<ash::Instance> = None;
-static mut VK_DEVICE: Option<ash::Device> = None;
+// Diagnostics go through the "log" crate. The verbosity can be controlled with the RUST_LOG environment variable,
+// for example "RUST_LOG=debug". Without it, everything at the given default level and above is shown.
+fn init_logger(default_filter: &str) {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter)).init();
+}
 
-fn main() {
+// Returns the number of validation errors reported over the whole session, teardown included.
+fn run(config: &Config) -> Result<u32, RendererError> {
     unsafe {
+        // Everything created below is handed over to the context as soon as it exists.
+        // If any step fails and returns early, dropping the context cleans up exactly what was created up to that point.
+        // At the end of the function, dropping it tears everything down in the order its fields are declared in.
+        let mut context = VulkanContext::default();
+
         if glfwInit() == 0 {
             panic!("Failed to initialize GLFW.");
         }
+        context.glfw = Some(GlfwGuard);
 
         // Vulkan Ash related initialization
         // TODO: Read up more on this Entry::Linked called. It seems to load the Vulkan library by linking to it statically.
         // But how does this work, and what exactly does it do???
-        VK_ENTRY = Some(Entry::linked());
-
-        /*
-            In order to initialize Vulkan, we need to create an instance.
-            The instance is a connection between your application and the Vulkan library.
-
-            To create an instance, you first have to fill out a struct with information about the application.
-            A lot of information in Vulkan will be passed through structs instead of function parameters.
-
-            The "ApplicationInfo" struct is technically optional, but giving the information may help the driver optimize some things for
-            our application.
-        */
-        let application_name = ffi_string("2D Shooter");
-        let engine_name = ffi_string("No Engine");
-
-        let application_info = vk::ApplicationInfo {
-            s_type: vk::StructureType::APPLICATION_INFO,
-            p_application_name: application_name.as_ptr(),
-            application_version: vk::make_api_version(1, 0, 0, 0),
-            p_engine_name: engine_name.as_ptr(),
-            engine_version: vk::make_api_version(1, 0, 0, 0),
-            api_version: vk::API_VERSION_1_0,
-            ..Default::default()
-        };
-
-        // vkInstanceCreateInfo is a required struct which tells the Vulkan driver which global extensions and validation layers we want to use.
-        // Global meaning: They apply to the entire program and not a specific device.
-        // We also specify our application info struct in this struct.
-        let required_extensions = build_extensions();
+        let entry: &Entry = context.entry.insert(Entry::linked());
 
-        // For debug builds, I'll enable standard validation layers that comes bundled with the LunarG Vulkan SDK.
+        // When validation is enabled, I'll enable standard validation layers that comes bundled with the LunarG Vulkan SDK.
         // These standard validations comes bundled into a layer in the SDK called "VK_LAYER_KHRONOS_validation".
-        let required_validation_layers = vec!(
-            "VK_LAYER_KHRONOS_validation"
-        );
-
-        // Retrieve all available layers.
-        // TODO: Probably I could transform available_layers to a list of strings to quickly compare against my required validation layers
-        let available_layers = VK_ENTRY.as_ref().unwrap().enumerate_instance_layer_properties().expect("Failed to retrieve available layers.");
-
-        for required_validation_layer in &required_validation_layers {
-            let mut is_required_validation_layer_supported = false;
-
-            for available_layer in &available_layers {
-                // TODO: Is this an owned string that is being converted to??
-                let layer_name = CStr::from_ptr(available_layer.layer_name.as_ptr()).to_str().expect("Failed to get string from available layer.");
-                if layer_name == (*required_validation_layer) {
-                    is_required_validation_layer_supported = true;
-                }
-            }
-
-            if !is_required_validation_layer_supported {
-                panic!("The required validation layer {} could not be found in the list of available layers.", required_validation_layer);
-            }
-        }
-
-        let validation_layers_as_cstrings : Vec<CString> = required_validation_layers
-            .iter()
-            .map(|layer_name| {
-                CString::new(*layer_name).unwrap()
-            })
-            .collect();
-
-        let validation_layers_as_raw_pointers: Vec<*const i8> = validation_layers_as_cstrings
-            .iter()
-            .map(|x| x.as_ptr())
-            .collect();
-
-        let required_extensions_as_c_string: Vec<CString> = required_extensions.iter()
-            .map(|x| CString::new(x.clone()).expect("Failed to create CString from string"))
-            .collect();
-
-        let required_extensions_pointer: Vec<*const i8> = required_extensions_as_c_string
-            .iter()
-            .map(|x| x.as_ptr())
-            .collect();
-
-        // The Debug Utils debug messenger requires a valid instance in order to be created. In order to enable debug callbacks when creating the instance,
-        // You can instead pass a DebugUtilsMessengerCreateInfoEXT object pointer to the InstanceCreateInfo struct's p_next property.
-        // TODO: Do I need to handle the lifetime of this instance debug messenger myself??
-        let instance_debug_messenger = populate_debug_messenger_create_info();
-
-        let create_info = vk::InstanceCreateInfo {
-            s_type: vk::StructureType::INSTANCE_CREATE_INFO,
-            p_application_info: &application_info,
-            enabled_extension_count: required_extensions_pointer.len() as u32,
-            pp_enabled_extension_names: required_extensions_pointer.as_ptr(),
-            pp_enabled_layer_names: validation_layers_as_raw_pointers.as_ptr(),
-            enabled_layer_count: required_validation_layers.len() as u32,
-            p_next: &instance_debug_messenger as *const vk::DebugUtilsMessengerCreateInfoEXT as *const c_void,
-            ..Default::default()
+        // By default, validation is enabled for debug builds. This can be overridden with the "--validation" argument.
+        let validation_enabled = config.validation != ValidationLevel::Off;
+        let required_validation_layers = if validation_enabled {
+            vec!("VK_LAYER_KHRONOS_validation")
+        } else {
+            vec!()
         };
 
-        // Now everything is specified for Vulkan to create an instance
+        // Every message the debug callback receives is counted here, so a summary can be printed at exit.
+        // The context keeps it alive until after the instance is destroyed, and this handle keeps it around after that, to return the final count.
+        let validation_stats = Arc::new(ValidationStats::default());
+        context.validation_stats = Some(ValidationStatsGuard {
+            stats: Arc::clone(&validation_stats),
+            report: validation_enabled
+        });
+
         // This instance should live for as long as the application lives.
-        // Creating a VkInstance object initializes the Vulkan library.
-        // Per-application state is stored in this object. Vulkan does NOT have any global state.
-        VK_INSTANCE = Some(VK_ENTRY.as_ref().unwrap().create_instance(&create_info, None).expect("Failed to create Vulkan instance."));
+        let instance: &ash::Instance = context.instance.insert(InstanceGuard(create_instance(entry, &required_validation_layers, config.validation, &validation_stats)?));
 
         // In order to create a debug messenger, we have to call the function "vkCreateDebugUtilsMessengerEXT"
         // Since this is an extension function, it is not automatically loaded with Vulkan.
         // We have to load it ourselves
-        let debug_utils_loader = ash::extensions::ext::DebugUtils::new(VK_ENTRY.as_ref().unwrap(), VK_INSTANCE.as_ref().unwrap());
-        let debug_utils_messenger = setup_debug_messenger(&debug_utils_loader);
+        // With validation disabled, VK_EXT_debug_utils isn't enabled on the instance, so neither the loader nor the messenger is created.
+        if validation_enabled {
+            let debug_utils_loader = ash::extensions::ext::DebugUtils::new(entry, instance);
+            let debug_utils_messenger = setup_debug_messenger(&debug_utils_loader, config.validation, &validation_stats)?;
+            context.debug_messenger = Some(DebugMessengerGuard { loader: debug_utils_loader, messenger: debug_utils_messenger });
+        }
 
         // After creating a Vulkan instance, we need to select a physical graphics card that supports the features we need.
-        let physical_devices = VK_INSTANCE.as_ref().unwrap().enumerate_physical_devices().expect("Failed to retrieve physical devices.");
+        let physical_devices = instance.enumerate_physical_devices().map_err(RendererError::PhysicalDevice)?;
+
+        // No devices at all is a different problem than no suitable devices, usually a missing or broken driver, so it gets its own error.
+        if physical_devices.is_empty() {
+            return Err(RendererError::NoVulkanDevices);
+        }
 
         // GLFW was originally designed to create an OpenGL context, so we have to tell it not to
         // since we'll be using Vulkan.
@@ -151,160 +126,509 @@ fn main() {
         // Disabled for now.
         glfwWindowHint(GLFW_RESIZABLE as i32, GLFW_FALSE as i32);
 
+        // A transparent framebuffer lets the window system blend the window contents with whatever is behind it.
+        // The swapchain has to be created with a matching composite alpha mode as well, for this to have any effect.
+        if config.transparent_framebuffer {
+            glfwWindowHint(GLFW_TRANSPARENT_FRAMEBUFFER as i32, GLFW_TRUE as i32);
+        }
+
+        // Passing a monitor to glfwCreateWindow makes the window fullscreen on that monitor.
+        let (window_width, window_height, window_monitor) = choose_window_placement(config.window_mode);
+
         let window_title = ffi_string("Two Dee Shooter");
-        let mut main_window = glfwCreateWindow(
-            WIDTH,
-            HEIGHT,
+        let main_window = glfwCreateWindow(
+            window_width,
+            window_height,
             window_title.as_ptr(),
-            ptr::null_mut(),
+            window_monitor,
             ptr::null_mut());       
 
         // If main_window is NULL, window creation failed for some reason.
         if main_window.is_null() {
             panic!("Failed to create window: {}", get_latest_glfw_error_description());
         }
+        context.window = Some(WindowGuard(main_window));
+
+        if let Some(window_icon_path) = &config.window_icon {
+            set_window_icon(main_window, window_icon_path);
+        }
+
+        let cursor_mode = match config.cursor_mode {
+            CursorMode::Normal => GLFW_CURSOR_NORMAL,
+            CursorMode::Hidden => GLFW_CURSOR_HIDDEN,
+            CursorMode::Disabled => GLFW_CURSOR_DISABLED
+        };
+        glfwSetInputMode(main_window, GLFW_CURSOR as i32, cursor_mode as i32);
 
         // In order to present visuals to the window, we need to create a VkSurfaceKHR object.
         // This object represents an abstract type of surface to present rendered images to.
         // While the object and its usage is platform agnostic, the creation isn't.
         // The creation depends on window system details, like a HWND and HMODULE.
         // There is a platform-specific addition to "VK_KHR_SURFACE" called "VK_KHR_win32_surface" that handles this.
-        let surface_extension = ash::extensions::khr::Surface::new(VK_ENTRY.as_ref().unwrap(), VK_INSTANCE.as_ref().unwrap());
+        let surface_guard = context.surface.insert(SurfaceGuard {
+            extension: ash::extensions::khr::Surface::new(entry, instance),
+            surface: create_surface(instance, main_window)?
+        });
+        let surface_extension = &surface_guard.extension;
+        let the_surface = surface_guard.surface;
 
-        let mut some_surface: u64 = 0;
+        // TODO: Do something nice here, like printing a list of all available physical devices.
+        let selected_physical_device = select_physical_device(instance, the_surface, surface_extension, &physical_devices, config)?;
 
-        // TODO: I manually edited the bindings.rs file to simply have u64 handles for parameters. The bindgen generation is bonkers.
-        // I'll have to figure out how to make that generation automatic, by modifying the types through the bindgen builder.
-        // Perhaps I should also raise an issue on bindgen github?
-        let result = glfwCreateWindowSurface(VK_INSTANCE.as_ref().unwrap().handle().as_raw(), main_window, ptr::null(), &mut some_surface);
+        // Captured once and kept on the context, so anything that wants to show it later doesn't have to query Vulkan again.
+        let gpu_info = context.gpu_info.insert(GpuInfo::capture(instance, selected_physical_device));
+        info!("Using GPU: {}", gpu_info);
 
-        if result != 0 {
-            panic!("Failed to create Window Surface!");
+        if config.list_surface_info {
+            print_surface_info(surface_extension, the_surface, selected_physical_device)?;
         }
 
-        let the_surface = vk::SurfaceKHR::from_raw(some_surface);        
+        // Time to create a logical device from our physical device!
+        let indices = find_queue_families(instance, the_surface, surface_extension, selected_physical_device);
 
-        // TODO: Do something nice here, like printing a list of all available physical devices.
-        let mut selected_physical_device: Option<vk::PhysicalDevice> = None;
-        for physical_device in physical_devices {
-            if is_device_suitable(VK_INSTANCE.as_ref().unwrap(), the_surface, &surface_extension, physical_device) {
-                selected_physical_device = Some(physical_device);
-            }
-        }
+        // Presentation support is queried per queue family and per surface.
+        // Double check that the family we are about to present from supports this exact surface, before building anything on top of it.
+        let present_family = indices.present_family.unwrap();
+        let is_present_supported = surface_extension
+            .get_physical_device_surface_support(selected_physical_device, present_family, the_surface)
+            .map_err(RendererError::SurfaceQuery)?;
 
-        if selected_physical_device.is_none() {
-            panic!("Failed to select a physical device!");
+        if !is_present_supported {
+            return Err(RendererError::PresentNotSupported(present_family));
         }
 
-        // Time to create a logical device from our physical device!
+        let graphics_queue_count = get_graphics_queue_count(instance, selected_physical_device, &indices, config.desired_graphics_queue_count);
+
+        let logical_device: &ash::Device = context.device.insert(DeviceGuard(create_logical_device(instance, selected_physical_device, &indices, graphics_queue_count, &config.required_features, &config.device_extensions, &required_validation_layers)?));
+
+        // Now that we have a logical device, we can retrieve the queues we need.
+        // Right now, we need the queue that supports presentation.
+        let device_presentation_queue = logical_device.get_device_queue(indices.present_family.unwrap(), 0);
+
+        // All queues created from the graphics family are kept in a pool.
+        // Only one is needed right now, but having several lets command buffers be recorded and submitted from multiple threads later on.
+        let graphics_queues: Vec<vk::Queue> = (0..graphics_queue_count)
+            .map(|queue_index| logical_device.get_device_queue(indices.graphics_family.unwrap(), queue_index))
+            .collect();
+
+        // Uploads go through the transfer queue.
+        // When it comes from a different family than the graphics queue, resources it uploads must be handed over to the graphics family
+        // with a queue family ownership transfer (a release barrier on the transfer queue and a matching acquire barrier on the graphics queue).
+        let transfer_queue = logical_device.get_device_queue(indices.transfer_family.unwrap(), 0);
 
-        // In order to create a logical device, I need to supply information on queues I want to have created, as well as
-        // Device features I want to use.
-        let indices = find_queue_families(VK_INSTANCE.as_ref().unwrap(), the_surface, &surface_extension, selected_physical_device.unwrap());
-
-        let mut family_indices: HashSet<u32> = HashSet::new();
-        family_indices.insert(indices.graphics_family.unwrap());
-        family_indices.insert(indices.present_family.unwrap());
-
-        // I run through each family index that I need to create a queue for, and create its DeviceQueueCreateInfo struct.
-        // The list of these DeviceQueueCreateInfo structs will be passed to DeviceCreateInfo struct, when creating the logical device and its
-        // required queues.
-        let mut queues_to_create: Vec<vk::DeviceQueueCreateInfo> = vec!();
-
-        // Vulkan requires that you assign priorities to queues, in order to influence the scheduling of command buffer execution.
-        // The priority is specified using a floating point number between 0.0 and 1.0.
-        // TODO: Read up more on this scheduling mechanism
-        let queue_priority: f32 = 1.0;
-
-        for family_index in family_indices {
-            let queue_create_info = vk::DeviceQueueCreateInfo {
-                s_type: vk::StructureType::DEVICE_QUEUE_CREATE_INFO,
-                queue_family_index: family_index,
-                queue_count: 1,
-                p_queue_priorities: &queue_priority,
-                ..Default::default()
-            };
-
-            queues_to_create.push(queue_create_info);
+        if indices.transfer_family != indices.graphics_family {
+            info!("Using transfer queue family {}, separate from the graphics queue family.", indices.transfer_family.unwrap());
+        } else {
+            info!("No separate transfer queue family found. Uploads will use the graphics queue family.");
         }
 
-        // We also need to supply information about device features we want.
-        // Right now, I don't need anything in particular, so I'll leave the struct with default values.
-        let device_features = vk::PhysicalDeviceFeatures {
-            ..Default::default()
-        };
+        let swapchain_extension = ash::extensions::khr::Swapchain::new(instance, logical_device);
 
-        // Required device extensions
-        let required_device_extensions: Vec<String> = REQUIRED_EXTENSIONS.clone().into_iter().collect();
-        let required_device_extensions_cstrings = strings_to_cstrings(required_device_extensions);
-        let required_device_extensions_raw_pointers = strings_to_raw_pointers(&required_device_extensions_cstrings);
-
-        // Now I create the logical device
-        // Qeues will be created automatically with the logical device.
-        let logical_device_create_info = vk::DeviceCreateInfo {
-            s_type: vk::StructureType::DEVICE_CREATE_INFO,
-            p_queue_create_infos: queues_to_create.as_ptr(),
-            queue_create_info_count: queues_to_create.len() as u32,
-            p_enabled_features: &device_features,
-            // Previous implementations of Vulkan made a distinction between instance and device specific validation layers,
-            // but this is no longer the case. "enabled_layer_count" and "pp_enabled_layer_names" are ignored by up-to-date implementations.
-            // However, it's a good idea to set the anyways to be compatible with older implementations.
-            enabled_layer_count: required_validation_layers.len() as u32,
-            pp_enabled_layer_names: validation_layers_as_raw_pointers.as_ptr(),
-            enabled_extension_count: required_device_extensions_raw_pointers.len() as u32,
-            pp_enabled_extension_names: required_device_extensions_raw_pointers.as_ptr(),
-            ..Default::default()
-        };
+        let swapchain = create_swap_chain(surface_extension, &swapchain_extension, the_surface, selected_physical_device, main_window, config.transparent_framebuffer, config.present_mode)?;
+        let swapchain_extension = &context.swapchain.insert(SwapchainGuard { extension: swapchain_extension, swapchain }).extension;
 
-        VK_DEVICE = Some( 
-            match VK_INSTANCE.as_ref().unwrap().create_device(selected_physical_device.unwrap(), &logical_device_create_info, None) {
-                Ok(physical_device) => physical_device,
-                Err(err) => panic!("Failed to create physical device: {}", err)
-            });
+        // Give the major objects readable names, so validation messages refer to them by name instead of only by handle.
+        if let Some(DebugMessengerGuard { loader: debug_utils_loader, .. }) = &context.debug_messenger {
+            set_debug_name(debug_utils_loader, logical_device, logical_device.handle(), "Logical Device");
+            set_debug_name(debug_utils_loader, logical_device, device_presentation_queue, "Presentation Queue");
+            set_debug_name(debug_utils_loader, logical_device, transfer_queue, "Transfer Queue");
+            for (queue_index, graphics_queue) in graphics_queues.iter().enumerate() {
+                set_debug_name(debug_utils_loader, logical_device, *graphics_queue, &format!("Graphics Queue {}", queue_index));
+            }
+            set_debug_name(debug_utils_loader, logical_device, the_surface, "Main Window Surface");
+            set_debug_name(debug_utils_loader, logical_device, swapchain, "Swapchain");
 
-        // Now that we have a logical device, we can retrieve the queue we need.
-        // Right now, we need the queue that supports presentation.
-        let device_presentation_queue = VK_DEVICE.as_ref().unwrap().get_device_queue(indices.present_family.unwrap(), 0);
+            let swapchain_images = swapchain_extension.get_swapchain_images(swapchain).map_err(RendererError::SwapchainImages)?;
+            for (image_index, swapchain_image) in swapchain_images.iter().enumerate() {
+                set_debug_name(debug_utils_loader, logical_device, *swapchain_image, &format!("Swapchain Image {}", image_index));
+            }
+        }
 
-        let swapchain_extension = ash::extensions::khr::Swapchain::new(VK_INSTANCE.as_ref().unwrap(), VK_DEVICE.as_ref().unwrap());
+        // With a frame rate cap, each iteration of the main loop is stretched to take at least this many seconds.
+        let target_frame_time = config.max_fps.map(|max_fps| 1.0 / max_fps as f64);
 
-        let swapchain = create_swap_chain(&surface_extension, &swapchain_extension, the_surface, selected_physical_device.unwrap(), main_window);
+        // A frame's time covers the whole iteration, including any waiting for the frame rate cap.
+        let mut frame_stats = FrameStats::new();
 
         while glfwWindowShouldClose(main_window) == 0 {
+            let frame_start = glfwGetTime();
+
             glfwPollEvents();
+
+            if let Some(target_frame_time) = target_frame_time {
+                wait_until(frame_start + target_frame_time);
+            }
+
+            frame_stats.record(glfwGetTime() - frame_start);
+
+            // Closing the window the usual way ends the loop on the next check, and still runs all the cleanup.
+            if config.frame_limit == Some(frame_stats.frame_count()) {
+                glfwSetWindowShouldClose(main_window, GLFW_TRUE as i32);
+            }
         }
 
-        // Delete the swapchain
-        swapchain_extension.destroy_swapchain(swapchain, None);
+        if config.frame_limit.is_some() {
+            info!("{}", frame_stats);
+        }
 
-        // Delete the logical device
-        VK_DEVICE.as_ref().unwrap().destroy_device(None);
+        // Tear everything down before reading the count, so messages reported during teardown are included.
+        drop(context);
 
-        // Clean up the debug messenger
-        // Destroying the debug messenger must be done before the Vulkan instance is destroyed.
-        // TODO: Does Ash handle any of these calls in Drop implementations of the structs??
-        debug_utils_loader.destroy_debug_utils_messenger(debug_utils_messenger, None);
+        Ok(validation_stats.error_count())
+    }
+}
 
-        // We destroy the KHR Surfance
-        surface_extension.destroy_surface(the_surface, None);
+// Decides the size of the main window, and which monitor it should be fullscreen on, if any.
+// Fullscreen windows are sized after the current video mode of their monitor, so the resolution of the desktop is kept.
+// For borderless fullscreen, the window hints also have to match the video mode, otherwise GLFW switches to exclusive fullscreen instead.
+unsafe fn choose_window_placement(window_mode: WindowMode) -> (i32, i32, *mut GLFWmonitor) {
+    let (monitor_index, borderless) = match window_mode {
+        WindowMode::Windowed => return (WIDTH, HEIGHT, ptr::null_mut()),
+        WindowMode::Fullscreen { monitor_index } => (monitor_index, false),
+        WindowMode::Borderless { monitor_index } => (monitor_index, true)
+    };
 
-        // Before we terminate the application, we destroy the Vulkan instance.
-        VK_INSTANCE.as_ref().unwrap().destroy_instance(None);
+    let monitor = select_monitor(monitor_index);
+    if monitor.is_null() {
+        warn!("No monitors found. Falling back to a {}x{} window.", WIDTH, HEIGHT);
+        return (WIDTH, HEIGHT, ptr::null_mut());
+    }
 
-        glfwDestroyWindow(main_window);
+    // GLFW returns NULL for the video mode if something went wrong while querying the monitor.
+    let video_mode = glfwGetVideoMode(monitor);
+    if video_mode.is_null() {
+        warn!("Failed to get the video mode of the monitor: {}. Falling back to a {}x{} window.", get_latest_glfw_error_description(), WIDTH, HEIGHT);
+        return (WIDTH, HEIGHT, ptr::null_mut());
+    }
+    let video_mode = &*video_mode;
 
-        // Before terminating your application, you should terminate the GLFW library if it has been initialized.
-        // If you don't global system settings changed by GLFW might not be restored properly.
-        glfwTerminate();
+    if borderless {
+        glfwWindowHint(GLFW_RED_BITS as i32, video_mode.redBits);
+        glfwWindowHint(GLFW_GREEN_BITS as i32, video_mode.greenBits);
+        glfwWindowHint(GLFW_BLUE_BITS as i32, video_mode.blueBits);
+        glfwWindowHint(GLFW_REFRESH_RATE as i32, video_mode.refreshRate);
     }
+
+    (video_mode.width, video_mode.height, monitor)
 }
 
-unsafe fn create_swap_chain(surface_extensions: &ash::extensions::khr::Surface, swapchain_extensions: &ash::extensions::khr::Swapchain, surface: vk::SurfaceKHR, device: vk::PhysicalDevice, window: *mut GLFWwindow) -> vk::SwapchainKHR {
-    let swap_chain_support_details = query_swapchain_support(surface_extensions, surface, device);
+// Returns the monitor with the given index, in the order GLFW lists them.
+// The primary monitor is always first in that list.
+// An index beyond the connected monitors falls back to the primary monitor, since monitors come and go between runs.
+unsafe fn select_monitor(monitor_index: usize) -> *mut GLFWmonitor {
+    let mut monitor_count = 0;
+    let monitors = glfwGetMonitors(&mut monitor_count);
+
+    if monitors.is_null() || monitor_index >= monitor_count as usize {
+        warn!("Monitor {} doesn't exist, only {} monitors are connected. Using the primary monitor instead.", monitor_index, monitor_count);
+        return glfwGetPrimaryMonitor();
+    }
+
+    *monitors.add(monitor_index)
+}
+
+// Sets the window icon from a PNG file.
+// A missing or broken icon isn't worth failing over, so in that case the window simply keeps its default icon.
+unsafe fn set_window_icon(window: *mut GLFWwindow, icon_path: &str) {
+    let (width, height, mut pixels) = match load_rgba_png(icon_path) {
+        Ok(icon) => icon,
+        Err(err) => {
+            warn!("Failed to load window icon {}: {}", icon_path, err);
+            return;
+        }
+    };
+
+    // GLFW copies the pixel data before returning, so the image only has to live for the duration of the call.
+    let icon = GLFWimage {
+        width: width as i32,
+        height: height as i32,
+        pixels: pixels.as_mut_ptr()
+    };
+
+    glfwSetWindowIcon(window, 1, &icon);
+}
+
+// Decodes a PNG into 8-bit RGBA pixels, which is the format GLFW expects for icons.
+fn load_rgba_png(path: &str) -> Result<(u32, u32, Vec<u8>), Box<dyn std::error::Error>> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+
+    // Expand palette and low bit depth images, and strip 16-bit channels down to 8 bits.
+    // After this, the only possible color types are grayscale, grayscale + alpha, RGB and RGBA.
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec!(0; reader.output_buffer_size());
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+
+    let rgba_pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer.chunks(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks(2).flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]]).collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|g| [*g, *g, *g, 255]).collect(),
+        color_type => return Err(format!("Unsupported PNG color type {:?}", color_type).into())
+    };
+
+    Ok((info.width, info.height, rgba_pixels))
+}
+
+// Window surface creation is the most platform dependent part of the setup, since GLFW has to call the window system specific function for us.
+// GLFW hands back the raw VkResult, which I map to ash's result type to get a readable error.
+unsafe fn create_surface(instance: &ash::Instance, window: *mut GLFWwindow) -> Result<vk::SurfaceKHR, RendererError> {
+    let mut some_surface: u64 = 0;
+
+    // TODO: I manually edited the bindings.rs file to simply have u64 handles for parameters. The bindgen generation is bonkers.
+    // I'll have to figure out how to make that generation automatic, by modifying the types through the bindgen builder.
+    // Perhaps I should also raise an issue on bindgen github?
+    let result = glfwCreateWindowSurface(instance.handle().as_raw(), window, ptr::null(), &mut some_surface);
+
+    if result != 0 {
+        return Err(RendererError::Surface(vk::Result::from_raw(result)));
+    }
+
+    Ok(vk::SurfaceKHR::from_raw(some_surface))
+}
+
+unsafe fn create_instance(entry: &Entry, required_validation_layers: &[&str], validation: ValidationLevel, validation_stats: &ValidationStats) -> Result<ash::Instance, RendererError> {
+    /*
+        In order to initialize Vulkan, we need to create an instance.
+        The instance is a connection between your application and the Vulkan library.
+
+        To create an instance, you first have to fill out a struct with information about the application.
+        A lot of information in Vulkan will be passed through structs instead of function parameters.
+
+        The "ApplicationInfo" struct is technically optional, but giving the information may help the driver optimize some things for
+        our application.
+    */
+    let application_name = ffi_string("2D Shooter");
+    let engine_name = ffi_string("No Engine");
+
+    let application_info = vk::ApplicationInfo {
+        s_type: vk::StructureType::APPLICATION_INFO,
+        p_application_name: application_name.as_ptr(),
+        application_version: vk::make_api_version(1, 0, 0, 0),
+        p_engine_name: engine_name.as_ptr(),
+        engine_version: vk::make_api_version(1, 0, 0, 0),
+        api_version: vk::API_VERSION_1_0,
+        ..Default::default()
+    };
+
+    // vkInstanceCreateInfo is a required struct which tells the Vulkan driver which global extensions and validation layers we want to use.
+    // Global meaning: They apply to the entire program and not a specific device.
+    // We also specify our application info struct in this struct.
+    let mut required_extensions = build_extensions(validation != ValidationLevel::Off);
+
+    let available_extensions = get_available_instance_extensions(entry)?;
+
+    // On platforms without a native Vulkan driver, such as macOS through MoltenVK, the implementation is only a "portability" implementation.
+    // These are not conformant with the full specification, and the loader won't report them unless the application explicitly
+    // opts into them by enabling VK_KHR_portability_enumeration and setting the ENUMERATE_PORTABILITY_KHR flag on the instance.
+    // The portability subset device extension also depends on VK_KHR_get_physical_device_properties2 when targeting Vulkan 1.0.
+    let mut instance_create_flags = vk::InstanceCreateFlags::empty();
+    if available_extensions.contains("VK_KHR_portability_enumeration") {
+        info!("Portability enumeration is available. Enabling portability implementations.");
+        required_extensions.push(String::from("VK_KHR_portability_enumeration"));
+        instance_create_flags |= vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR;
+
+        if available_extensions.contains("VK_KHR_get_physical_device_properties2") {
+            required_extensions.push(String::from("VK_KHR_get_physical_device_properties2"));
+        }
+    }
+
+    // If a required extension isn't available, create_instance would fail with a generic ERROR_EXTENSION_NOT_PRESENT.
+    // So, just as with the validation layers below, I check for them up front to be able to tell exactly which ones are missing.
+    let missing_extensions: Vec<String> = required_extensions
+        .iter()
+        .filter(|required_extension| !available_extensions.contains(required_extension.as_str()))
+        .cloned()
+        .collect();
+
+    if !missing_extensions.is_empty() {
+        for missing_extension in &missing_extensions {
+            error!("Required instance extension {} is not available.", missing_extension);
+        }
+
+        return Err(RendererError::MissingInstanceExtensions(missing_extensions));
+    }
+
+    // Retrieve all available layers.
+    // TODO: Probably I could transform available_layers to a list of strings to quickly compare against my required validation layers
+    let available_layers = entry.enumerate_instance_layer_properties().map_err(RendererError::Instance)?;
+
+    for required_validation_layer in required_validation_layers {
+        let mut is_required_validation_layer_supported = false;
+
+        for available_layer in &available_layers {
+            // Names come from the driver and aren't guaranteed to be valid UTF-8.
+            // to_string_lossy() replaces invalid bytes instead of failing, and only allocates if it actually has to.
+            let layer_name = CStr::from_ptr(available_layer.layer_name.as_ptr()).to_string_lossy();
+            if layer_name == *required_validation_layer {
+                is_required_validation_layer_supported = true;
+            }
+        }
+
+        if !is_required_validation_layer_supported {
+            return Err(RendererError::MissingValidationLayer(required_validation_layer.to_string()));
+        }
+    }
+
+    let validation_layers_as_cstrings : Vec<CString> = required_validation_layers
+        .iter()
+        .map(|layer_name| {
+            CString::new(*layer_name).unwrap()
+        })
+        .collect();
+
+    let validation_layers_as_raw_pointers: Vec<*const i8> = validation_layers_as_cstrings
+        .iter()
+        .map(|x| x.as_ptr())
+        .collect();
+
+    let required_extensions_as_c_string: Vec<CString> = required_extensions.iter()
+        .map(|x| CString::new(x.clone()).expect("Failed to create CString from string"))
+        .collect();
+
+    let required_extensions_pointer: Vec<*const i8> = required_extensions_as_c_string
+        .iter()
+        .map(|x| x.as_ptr())
+        .collect();
+
+    // The Debug Utils debug messenger requires a valid instance in order to be created. In order to enable debug callbacks when creating the instance,
+    // You can instead pass a DebugUtilsMessengerCreateInfoEXT object pointer to the InstanceCreateInfo struct's p_next property.
+    // TODO: Do I need to handle the lifetime of this instance debug messenger myself??
+    let instance_debug_messenger = populate_debug_messenger_create_info(validation, validation_stats);
+
+    let instance_create_info_next = if validation != ValidationLevel::Off {
+        &instance_debug_messenger as *const vk::DebugUtilsMessengerCreateInfoEXT as *const c_void
+    } else {
+        ptr::null()
+    };
+
+    let create_info = vk::InstanceCreateInfo {
+        s_type: vk::StructureType::INSTANCE_CREATE_INFO,
+        flags: instance_create_flags,
+        p_application_info: &application_info,
+        enabled_extension_count: required_extensions_pointer.len() as u32,
+        pp_enabled_extension_names: required_extensions_pointer.as_ptr(),
+        pp_enabled_layer_names: validation_layers_as_raw_pointers.as_ptr(),
+        enabled_layer_count: required_validation_layers.len() as u32,
+        p_next: instance_create_info_next,
+    };
+
+    // Now everything is specified for Vulkan to create an instance
+    // Creating a VkInstance object initializes the Vulkan library.
+    // Per-application state is stored in this object. Vulkan does NOT have any global state.
+    entry.create_instance(&create_info, None).map_err(RendererError::Instance)
+}
+
+// Figures out how many queues to create from the graphics family.
+// This is the desired amount, capped by how many queues the family actually supports.
+unsafe fn get_graphics_queue_count(instance: &ash::Instance, physical_device: vk::PhysicalDevice, indices: &QueueFamilyIndices, desired_queue_count: u32) -> u32 {
+    let queue_families = instance.get_physical_device_queue_family_properties(physical_device);
+    let available_queue_count = queue_families[indices.graphics_family.unwrap() as usize].queue_count;
+
+    let queue_count = available_queue_count.min(desired_queue_count.max(1));
+    if queue_count < desired_queue_count {
+        warn!("Requested {} graphics queues, but the graphics family only supports {}.", desired_queue_count, available_queue_count);
+    }
+
+    queue_count
+}
+
+unsafe fn create_logical_device(instance: &ash::Instance, physical_device: vk::PhysicalDevice, indices: &QueueFamilyIndices, graphics_queue_count: u32, required_features: &RequiredFeatures, required_device_extensions: &[String], required_validation_layers: &[&str]) -> Result<ash::Device, RendererError> {
+    // In order to create a logical device, I need to supply information on queues I want to have created, as well as
+    // Device features I want to use.
+    let mut family_indices: HashSet<u32> = HashSet::new();
+    family_indices.insert(indices.graphics_family.unwrap());
+    family_indices.insert(indices.present_family.unwrap());
+    family_indices.insert(indices.transfer_family.unwrap());
+
+    // I run through each family index that I need to create a queue for, and create its DeviceQueueCreateInfo struct.
+    // The list of these DeviceQueueCreateInfo structs will be passed to DeviceCreateInfo struct, when creating the logical device and its
+    // required queues.
+    let mut queues_to_create: Vec<vk::DeviceQueueCreateInfo> = vec!();
+
+    // Vulkan requires that you assign priorities to queues, in order to influence the scheduling of command buffer execution.
+    // The priority is specified using a floating point number between 0.0 and 1.0.
+    // TODO: Read up more on this scheduling mechanism
+    // "p_queue_priorities" must point to an array with one priority per queue created from the family.
+    // Each family gets its own list of priorities, and the lists must stay alive until the device has been created,
+    // since the create info structs only store raw pointers into them.
+    let family_indices: Vec<u32> = family_indices.into_iter().collect();
+    // The graphics family gets "graphics_queue_count" queues. If presentation happens on the same family, it simply uses the first of them.
+    let queue_priorities: Vec<Vec<f32>> = family_indices
+        .iter()
+        .map(|family_index| {
+            if *family_index == indices.graphics_family.unwrap() {
+                vec!(1.0; graphics_queue_count as usize)
+            } else {
+                vec!(1.0)
+            }
+        })
+        .collect();
+
+    for (family_index, family_queue_priorities) in family_indices.iter().zip(&queue_priorities) {
+        let queue_create_info = vk::DeviceQueueCreateInfo {
+            s_type: vk::StructureType::DEVICE_QUEUE_CREATE_INFO,
+            queue_family_index: *family_index,
+            queue_count: family_queue_priorities.len() as u32,
+            p_queue_priorities: family_queue_priorities.as_ptr(),
+            ..Default::default()
+        };
+
+        queues_to_create.push(queue_create_info);
+    }
+
+    // We also need to supply information about device features we want.
+    // These are exactly the required features from the config. Everything else is left disabled.
+    let device_features = get_enabled_features(required_features);
+
+    let validation_layers_as_cstrings: Vec<CString> = required_validation_layers
+        .iter()
+        .map(|layer_name| CString::new(*layer_name).unwrap())
+        .collect();
+    let validation_layers_as_raw_pointers = strings_to_raw_pointers(&validation_layers_as_cstrings);
+
+    // Required device extensions, as configured. The suitability check has already made sure the device supports all of them.
+    let mut required_device_extensions: Vec<String> = required_device_extensions.to_vec();
 
-    let surface_format = choose_swap_surface_format(swap_chain_support_details.formats);
-    let present_mode = choose_swap_present_mode(swap_chain_support_details.presentModes);
+    // The Vulkan spec requires that if a device exposes VK_KHR_portability_subset, the application must enable it.
+    // This is the case for MoltenVK, which only supports a subset of Vulkan on top of Metal.
+    let portability_subset = String::from("VK_KHR_portability_subset");
+    if !required_device_extensions.contains(&portability_subset) && check_device_extension_support(instance, physical_device, std::slice::from_ref(&portability_subset))?.is_empty() {
+        info!("Device exposes VK_KHR_portability_subset. Enabling it.");
+        required_device_extensions.push(portability_subset);
+    }
+    let required_device_extensions_cstrings = strings_to_cstrings(required_device_extensions);
+    let required_device_extensions_raw_pointers = strings_to_raw_pointers(&required_device_extensions_cstrings);
+
+    // Now I create the logical device
+    // Qeues will be created automatically with the logical device.
+    let logical_device_create_info = vk::DeviceCreateInfo {
+        s_type: vk::StructureType::DEVICE_CREATE_INFO,
+        p_queue_create_infos: queues_to_create.as_ptr(),
+        queue_create_info_count: queues_to_create.len() as u32,
+        p_enabled_features: &device_features,
+        // Previous implementations of Vulkan made a distinction between instance and device specific validation layers,
+        // but this is no longer the case. "enabled_layer_count" and "pp_enabled_layer_names" are ignored by up-to-date implementations.
+        // However, it's a good idea to set the anyways to be compatible with older implementations.
+        enabled_layer_count: required_validation_layers.len() as u32,
+        pp_enabled_layer_names: validation_layers_as_raw_pointers.as_ptr(),
+        enabled_extension_count: required_device_extensions_raw_pointers.len() as u32,
+        pp_enabled_extension_names: required_device_extensions_raw_pointers.as_ptr(),
+        ..Default::default()
+    };
+
+    instance.create_device(physical_device, &logical_device_create_info, None).map_err(RendererError::Device)
+}
+
+unsafe fn create_swap_chain(surface_extensions: &ash::extensions::khr::Surface, swapchain_extensions: &ash::extensions::khr::Swapchain, surface: vk::SurfaceKHR, device: vk::PhysicalDevice, window: *mut GLFWwindow, transparent: bool, present_mode_preference: PresentModePreference) -> Result<vk::SwapchainKHR, RendererError> {
+    let swap_chain_support_details = query_swapchain_support(surface_extensions, surface, device)?;
+
+    let surface_format = choose_swap_surface_format(swap_chain_support_details.formats)?;
+    let present_mode = choose_swap_present_mode(swap_chain_support_details.present_modes, present_mode_preference);
     let extent = choose_swap_extent(window, swap_chain_support_details.capabilities);
+    let composite_alpha = choose_composite_alpha(swap_chain_support_details.capabilities.supported_composite_alpha, transparent);
 
     // We need to decide how many images we would like to have in the swap chain.
     // capabilities.min_image_count specifies the minimum number of images the implementation requires to function.
@@ -312,11 +636,23 @@ unsafe fn create_swap_chain(surface_extensions: &ash::extensions::khr::Surface,
     // internal operations before we can aquire another image to render to.
     let mut image_count = swap_chain_support_details.capabilities.min_image_count + 1;
 
+    // MAILBOX is effectively triple buffering. One image is being presented, one is queued for presentation, and one is being rendered to.
+    // With fewer than three images, some drivers report validation warnings, and we lose the benefit of the present mode.
+    // So the image count is chosen together with the present mode, rather than independently of it.
+    if present_mode == vk::PresentModeKHR::MAILBOX {
+        image_count = image_count.max(3);
+    }
+
     // However, we still need to make sure that we do not exceed the maximum supported image count.
+    // A max_image_count of 0 means that there is no maximum.
     if swap_chain_support_details.capabilities.max_image_count > 0 && image_count > swap_chain_support_details.capabilities.max_image_count {
         image_count = swap_chain_support_details.capabilities.max_image_count;
     }
 
+    if present_mode == vk::PresentModeKHR::MAILBOX && image_count < 3 {
+        warn!("MAILBOX present mode selected, but the surface only allows {} swapchain images. Expected at least 3.", image_count);
+    }
+
     // The "image_array_layers" property specifies the amount of layers each image consists of.
     // This is always "1", unless you are developing a stereoscopic 3D application.
     // The "image_usage" property specificies what operations we'll use the images in the swap chain for.
@@ -325,6 +661,7 @@ unsafe fn create_swap_chain(surface_extensions: &ash::extensions::khr::Surface,
     // To specify that you do not want any transformation, simply specify the current transformation.
     // "composite_alpha" can be used to specify if the alpha channel should be used for blending with other windows in the window system.
     // You'll almost always want to simply ignore the alpha channel, which is "vk::CompositeAlphaFlagsKHR::OPAQUE".
+    // The exception is a transparent window, see "choose_composite_alpha".
     // TODO: Read up on "old_swapchain", complex topic regarding recreation of swap_chains in events such as resizing of window.
     let swap_chain_create_info = vk::SwapchainCreateInfoKHR {
         s_type: vk::StructureType::SWAPCHAIN_CREATE_INFO_KHR,
@@ -336,17 +673,46 @@ unsafe fn create_swap_chain(surface_extensions: &ash::extensions::khr::Surface,
         image_array_layers: 1,
         image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
         pre_transform: swap_chain_support_details.capabilities.current_transform,
-        composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
+        composite_alpha,
         present_mode: present_mode,
         clipped: vk::TRUE,
         old_swapchain: vk::SwapchainKHR::null(),
         ..Default::default()
     };
 
-    match swapchain_extensions.create_swapchain(&swap_chain_create_info, None) {
-        Ok(swapchain) => swapchain,
-        Err(e) => panic!("Failed to create swapchain: {}", e)
+    swapchain_extensions.create_swapchain(&swap_chain_create_info, None).map_err(RendererError::Swapchain)
+}
+
+// Picks how the swapchain images are blended with the other windows in the window system.
+// For an opaque window the alpha channel is ignored.
+// For a transparent window, I prefer PRE_MULTIPLIED, which expects the color channels to already be multiplied by alpha.
+// POST_MULTIPLIED, where the compositor multiplies the color channels by alpha itself, is the second choice.
+// Support for either is up to the window system, so if neither is available the window stays opaque.
+// Not every surface offers OPAQUE either. Some only support INHERIT, where the window system decides through its own means.
+// So rather than assuming OPAQUE, I fall back to the first mode the surface actually supports, in this order.
+fn choose_composite_alpha(supported_composite_alpha: vk::CompositeAlphaFlagsKHR, transparent: bool) -> vk::CompositeAlphaFlagsKHR {
+    if transparent {
+        for composite_alpha in [vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED, vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED] {
+            if supported_composite_alpha.contains(composite_alpha) {
+                return composite_alpha;
+            }
+        }
+
+        warn!("Transparent window requested, but the surface only supports {:?} composite alpha. Falling back to a non-transparent window.", supported_composite_alpha);
     }
+
+    let fallback_composite_alphas = [
+        vk::CompositeAlphaFlagsKHR::OPAQUE,
+        vk::CompositeAlphaFlagsKHR::INHERIT,
+        vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+        vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED
+    ];
+
+    // The spec guarantees that at least one mode is supported, so OPAQUE at the end is only there to have something to return.
+    fallback_composite_alphas
+        .into_iter()
+        .find(|composite_alpha| supported_composite_alpha.contains(*composite_alpha))
+        .unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE)
 }
 
 // VkSurfaceFormatKHR contains two properties:
@@ -355,27 +721,40 @@ unsafe fn create_swap_chain(surface_extensions: &ash::extensions::khr::Surface,
 // Format describes the color channels and types.
 // colorSpace indicates if the SRGB color space is supported or not.
 // Right now, I will always prefer the format B8G8R8A8_SRGB with SRGB colorspace.
-fn choose_swap_surface_format(available_formats: Vec<vk::SurfaceFormatKHR>) -> vk::SurfaceFormatKHR {
+fn choose_swap_surface_format(available_formats: Vec<vk::SurfaceFormatKHR>) -> Result<vk::SurfaceFormatKHR, RendererError> {
     for surface_format in &available_formats {
         if surface_format.format == vk::Format::B8G8R8A8_SRGB && surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR {
-            println!("Picked preferred format and colorspace: B8G8R8A8_SRGB & SRGB");
-            return *surface_format;
+            info!("Picked preferred format and colorspace: B8G8R8A8_SRGB & SRGB");
+            return Ok(*surface_format);
         }
     }
 
     // In case the preferred case isn't available, we'll pick whatever is the first available format.
-    *available_formats.first().unwrap()
+    // Colors may then look different from what they were authored as, so make that visible.
+    // The suitability check rejects devices without any formats, so an empty list should never happen here.
+    let fallback_format = *available_formats.first().ok_or(RendererError::NoSurfaceFormats)?;
+    warn!("Preferred format and colorspace B8G8R8A8_SRGB & SRGB isn't available. Using {:?} & {:?} instead.", fallback_format.format, fallback_format.color_space);
+
+    Ok(fallback_format)
 }
 
-fn choose_swap_present_mode(available_present_modes: Vec<vk::PresentModeKHR>) -> vk::PresentModeKHR {
-    for present_mode in &available_present_modes {
-        // Currently prefer the MAILBOX present mode (similar to triple buffering)
-        if *present_mode == vk::PresentModeKHR::MAILBOX {
-            return *present_mode;
-        }
+fn choose_swap_present_mode(available_present_modes: Vec<vk::PresentModeKHR>, preference: PresentModePreference) -> vk::PresentModeKHR {
+    // By default I prefer the MAILBOX present mode (similar to triple buffering)
+    let preferred_present_mode = match preference {
+        PresentModePreference::Mailbox => vk::PresentModeKHR::MAILBOX,
+        PresentModePreference::FifoRelaxed => vk::PresentModeKHR::FIFO_RELAXED,
+        PresentModePreference::Fifo => vk::PresentModeKHR::FIFO
+    };
+
+    if available_present_modes.contains(&preferred_present_mode) {
+        return preferred_present_mode;
+    }
+
+    if preference != PresentModePreference::Mailbox {
+        info!("Present mode {:?} isn't available. Using FIFO instead.", preferred_present_mode);
     }
 
-    // If triple buffering isn't available we will prefer FIFO.
+    // If the preferred mode isn't available we will use FIFO.
     // This presentation mode is the only one guarenteed to be available.
     vk::PresentModeKHR::FIFO
 }
@@ -385,6 +764,16 @@ fn choose_swap_present_mode(available_present_modes: Vec<vk::PresentModeKHR>) ->
 // IN PIXELS.
 // The range of possible resolutions is defined in the vk::SurfaceCapabilitiesKHR structure.
 unsafe fn choose_swap_extent(window: *mut GLFWwindow, capabilities: vk::SurfaceCapabilitiesKHR) -> vk::Extent2D {
+    let mut width: i32 = 0;
+    let mut height: i32 = 0;
+
+    glfwGetFramebufferSize(window, &mut width, &mut height);
+
+    compute_extent((width as u32, height as u32), capabilities)
+}
+
+// The GLFW independent part of choosing the swap extent, given the framebuffer size in pixels.
+fn compute_extent(framebuffer_size: (u32, u32), capabilities: vk::SurfaceCapabilitiesKHR) -> vk::Extent2D {
     // If the width or height is not the maximum allowed value of u32,
     // This means that Vulkan has matched the resolution of the window
     // Otherwise, we need to pick the resolution that best matches the window within
@@ -392,46 +781,66 @@ unsafe fn choose_swap_extent(window: *mut GLFWwindow, capabilities: vk::SurfaceC
     // possible resolutions as determined by Vulkan.
     if capabilities.current_extent.width != u32::MAX {
         return capabilities.current_extent;
-    } else {
-        let mut width: i32 = 0;
-        let mut height: i32 = 0;
+    }
 
-        glfwGetFramebufferSize(window, &mut width, &mut height);
+    let (width, height) = framebuffer_size;
 
-        let actual_extent = vk::Extent2D {
-            width: clamp(width as u32, capabilities.min_image_extent.width, capabilities.max_image_extent.width),
-            height: clamp(height as u32, capabilities.min_image_extent.height, capabilities.max_image_extent.height),
-        };
+    // Ord::clamp panics if min is greater than max, so catch bogus capability bounds early in debug builds.
+    debug_assert!(capabilities.min_image_extent.width <= capabilities.max_image_extent.width, "Surface reported a min image width larger than its max image width.");
+    debug_assert!(capabilities.min_image_extent.height <= capabilities.max_image_extent.height, "Surface reported a min image height larger than its max image height.");
 
-        actual_extent
+    vk::Extent2D {
+        width: width.clamp(capabilities.min_image_extent.width, capabilities.max_image_extent.width),
+        height: height.clamp(capabilities.min_image_extent.height, capabilities.max_image_extent.height),
     }
 }
 
-fn clamp(value: u32, min: u32, max: u32) -> u32 {
-    if value < min {
-        return min;
-    } else if value > max {
-        return max;
-    }
-
-    value
-}
-
 #[derive(Default)]
-struct SwapChainSupportDetails {
-    capabilities: vk::SurfaceCapabilitiesKHR,
-    formats: Vec<vk::SurfaceFormatKHR>,
-    presentModes: Vec<vk::PresentModeKHR>
+pub struct SwapChainSupportDetails {
+    pub capabilities: vk::SurfaceCapabilitiesKHR,
+    pub formats: Vec<vk::SurfaceFormatKHR>,
+    pub present_modes: Vec<vk::PresentModeKHR>
 }
 
-unsafe fn query_swapchain_support(surface_extensions: &ash::extensions::khr::Surface, surface: vk::SurfaceKHR, device: vk::PhysicalDevice) -> SwapChainSupportDetails {
+// The surface and the physical device must be valid handles from the same instance.
+pub(crate) unsafe fn query_swapchain_support(surface_extensions: &ash::extensions::khr::Surface, surface: vk::SurfaceKHR, device: vk::PhysicalDevice) -> Result<SwapChainSupportDetails, RendererError> {
     let swapchain_support_details = SwapChainSupportDetails {
-        capabilities: surface_extensions.get_physical_device_surface_capabilities(device, surface).unwrap(),
-        formats: surface_extensions.get_physical_device_surface_formats(device, surface).unwrap(),
-        presentModes: surface_extensions.get_physical_device_surface_present_modes(device, surface).unwrap()
+        capabilities: surface_extensions.get_physical_device_surface_capabilities(device, surface).map_err(RendererError::SurfaceQuery)?,
+        formats: surface_extensions.get_physical_device_surface_formats(device, surface).map_err(RendererError::SurfaceQuery)?,
+        present_modes: surface_extensions.get_physical_device_surface_present_modes(device, surface).map_err(RendererError::SurfaceQuery)?
     };
 
-    swapchain_support_details
+    Ok(swapchain_support_details)
+}
+
+// Prints everything the surface supports on the given device.
+// Helps with understanding why a particular format, present mode or image count was chosen for the swapchain.
+unsafe fn print_surface_info(surface_extensions: &ash::extensions::khr::Surface, surface: vk::SurfaceKHR, device: vk::PhysicalDevice) -> Result<(), RendererError> {
+    let swap_chain_support_details = query_swapchain_support(surface_extensions, surface, device)?;
+    let capabilities = swap_chain_support_details.capabilities;
+
+    info!("Surface formats:");
+    for surface_format in &swap_chain_support_details.formats {
+        info!("    {:?} / {:?}", surface_format.format, surface_format.color_space);
+    }
+
+    info!("Present modes:");
+    for present_mode in &swap_chain_support_details.present_modes {
+        info!("    {:?}", present_mode);
+    }
+
+    // A max image count of 0 means that there is no limit.
+    info!("Surface capabilities:");
+    info!("    Image count: {} to {}", capabilities.min_image_count, capabilities.max_image_count);
+    info!("    Current extent: {}x{}", capabilities.current_extent.width, capabilities.current_extent.height);
+    info!("    Image extent: {}x{} to {}x{}",
+        capabilities.min_image_extent.width, capabilities.min_image_extent.height,
+        capabilities.max_image_extent.width, capabilities.max_image_extent.height);
+    info!("    Current transform: {:?}", capabilities.current_transform);
+    info!("    Supported transforms: {:?}", capabilities.supported_transforms);
+    info!("    Supported composite alpha: {:?}", capabilities.supported_composite_alpha);
+
+    Ok(())
 }
 
 fn strings_to_cstrings(strings: Vec<String>) -> Vec<CString> {
@@ -455,13 +864,12 @@ unsafe fn strings_to_raw_pointers(strings: &Vec<CString>) -> Vec<*const i8> {
 #[derive(Default)]
 struct QueueFamilyIndices {
     graphics_family: Option<u32>,
-    present_family: Option<u32>
-}
-
-impl QueueFamilyIndices {
-    pub fn is_complete(&self) -> bool {
-        self.graphics_family.is_some() && self.present_family.is_some()
-    }
+    present_family: Option<u32>,
+    // Family used for buffer and texture uploads.
+    // A family with TRANSFER but neither GRAPHICS nor COMPUTE is usually backed by a dedicated DMA engine, which lets uploads overlap with rendering.
+    // On AMD and others, a TRANSFER family without GRAPHICS but with COMPUTE is the async compute family instead, which is only the second choice.
+    // If there is neither, this falls back to the graphics family, which implicitly supports transfers as well.
+    transfer_family: Option<u32>
 }
 
 unsafe fn find_queue_families(instance: &ash::Instance, surface: vk::SurfaceKHR, khr_extension: &ash::extensions::khr::Surface, physical_device: vk::PhysicalDevice) -> QueueFamilyIndices {
@@ -473,76 +881,234 @@ unsafe fn find_queue_families(instance: &ash::Instance, surface: vk::SurfaceKHR,
     // Right now, we need to find a queue that supports VK_QUEUE_GRAPHICS_BIT
     let queue_families = instance.get_physical_device_queue_family_properties(physical_device);
 
+    // All families are visited, rather than stopping at the first complete set, since a dedicated transfer family can be anywhere in the list.
+    let mut compute_transfer_family: Option<u32> = None;
     let mut current_family_index: u32 = 0;
     for queue_family in queue_families {
-        if queue_family.queue_flags & vk::QueueFlags::GRAPHICS == vk::QueueFlags::GRAPHICS {
-            println!("Detected queue family supporting GRAPHICS");
+        let supports_graphics = queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS);
+        let supports_compute = queue_family.queue_flags.contains(vk::QueueFlags::COMPUTE);
+        let supports_transfer = queue_family.queue_flags.contains(vk::QueueFlags::TRANSFER);
+
+        if supports_graphics && indices.graphics_family.is_none() {
+            debug!("Detected queue family supporting GRAPHICS");
             indices.graphics_family = Some(current_family_index);
         }
 
+        if supports_transfer && !supports_graphics {
+            if !supports_compute && indices.transfer_family.is_none() {
+                indices.transfer_family = Some(current_family_index);
+            } else if supports_compute && compute_transfer_family.is_none() {
+                compute_transfer_family = Some(current_family_index);
+            }
+        }
+
         // It is actually possible that the queue families supporting drawing commands and the ones supporting presentation do not overlap.
         // There, we need to store distinct indices for drawing and presentation queues.
         // Here, I query for presentation support.
-        if khr_extension.get_physical_device_surface_support(physical_device, current_family_index, surface).is_ok() {
+        // The query returns whether the family supports presenting to the surface, so both the call and the answer have to succeed.
+        if indices.present_family.is_none() && khr_extension.get_physical_device_surface_support(physical_device, current_family_index, surface) == Ok(true) {
             indices.present_family = Some(current_family_index);
         }
 
-        if indices.is_complete() {
-            break;
-        }
-
         current_family_index += 1;
     }
 
+    if indices.transfer_family.is_none() {
+        indices.transfer_family = compute_transfer_family.or(indices.graphics_family);
+    }
+
     indices
 }
 
-unsafe fn is_device_suitable(instance: &ash::Instance, surface: vk::SurfaceKHR, khr_extension: &ash::extensions::khr::Surface, device: vk::PhysicalDevice) -> bool {
+// Picks the physical device to use.
+// A GPU can be forced through the config, either by its index in the list of physical devices, or by a substring of its name.
+// A forced GPU still has to be suitable. If it isn't, or if nothing is forced, we fall back to the last suitable device in the list.
+// If no device is suitable at all, the error lists why each device was rejected.
+unsafe fn select_physical_device(instance: &ash::Instance, surface: vk::SurfaceKHR, khr_extension: &ash::extensions::khr::Surface, physical_devices: &[vk::PhysicalDevice], config: &Config) -> Result<vk::PhysicalDevice, RendererError> {
+    if let Some(preferred_gpu_index) = config.preferred_gpu_index {
+        match physical_devices.get(preferred_gpu_index) {
+            Some(&physical_device) => {
+                let reasons = is_device_suitable(instance, surface, khr_extension, physical_device, &config.required_features, &config.device_extensions)?;
+                if reasons.is_empty() {
+                    info!("Selected physical device by preferred index {}.", preferred_gpu_index);
+                    return Ok(physical_device);
+                }
+
+                warn!("Physical device at preferred index {} isn't suitable ({}). Falling back.", preferred_gpu_index, join_reasons(&reasons));
+            },
+            None => warn!("There is no physical device at preferred index {}. Falling back.", preferred_gpu_index)
+        }
+    }
+
+    if let Some(preferred_gpu_name) = &config.preferred_gpu_name {
+        for &physical_device in physical_devices {
+            if get_device_name(instance, physical_device).contains(preferred_gpu_name.as_str()) && is_device_suitable(instance, surface, khr_extension, physical_device, &config.required_features, &config.device_extensions)?.is_empty() {
+                info!("Selected physical device by preferred name \"{}\".", preferred_gpu_name);
+                return Ok(physical_device);
+            }
+        }
+
+        warn!("No suitable physical device matched preferred name \"{}\". Falling back.", preferred_gpu_name);
+    }
+
+    let mut selected_physical_device: Option<vk::PhysicalDevice> = None;
+    let mut rejected_devices: Vec<(String, Vec<UnsuitableReason>)> = vec!();
+    for &physical_device in physical_devices {
+        let reasons = is_device_suitable(instance, surface, khr_extension, physical_device, &config.required_features, &config.device_extensions)?;
+        if reasons.is_empty() {
+            selected_physical_device = Some(physical_device);
+        } else {
+            rejected_devices.push((get_device_name(instance, physical_device), reasons));
+        }
+    }
+
+    if let Some(physical_device) = selected_physical_device {
+        info!("Selected physical device by default suitability check.");
+        return Ok(physical_device);
+    }
+
+    // If a required feature isn't supported by any device at all, that is the most useful thing to report.
+    let unsupported_features = get_features_missing_on_all_devices(instance, physical_devices, &config.required_features);
+    if !unsupported_features.is_empty() {
+        return Err(RendererError::MissingDeviceFeatures(unsupported_features));
+    }
+
+    Err(RendererError::NoSuitableDevice(rejected_devices))
+}
+
+fn join_reasons(reasons: &[UnsuitableReason]) -> String {
+    reasons.iter().map(|reason| reason.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+// Checks whether a physical device has everything we need.
+// Instead of stopping at the first problem, every reason for rejecting the device is collected, to make selection failures easy to diagnose.
+// An empty list means the device is suitable. An error means one of the queries itself failed.
+unsafe fn is_device_suitable(instance: &ash::Instance, surface: vk::SurfaceKHR, khr_extension: &ash::extensions::khr::Surface, device: vk::PhysicalDevice, required_features: &RequiredFeatures, required_extensions: &[String]) -> Result<Vec<UnsuitableReason>, RendererError> {
     let device_properties = instance.get_physical_device_properties(device);
     let device_features = instance.get_physical_device_features(device);
 
-    let extensions_supported = check_device_extension_support(instance, device);
+    // Device names come from the driver, and aren't guaranteed to be valid UTF-8.
+    let device_name = CStr::from_ptr(device_properties.device_name.as_ptr()).to_string_lossy();
+    // Selection can check the same device more than once, through the preferred index, the preferred name and the fallback.
+    // So these are only debug messages. The final selection, or the reasons every device was rejected, are reported on their own.
+    debug!("Checking physical device: {}", device_name);
+
+    let mut reasons: Vec<UnsuitableReason> = vec!();
+
+    if device_properties.device_type != vk::PhysicalDeviceType::DISCRETE_GPU {
+        reasons.push(UnsuitableReason::NotDiscreteGpu);
+    }
+
+    if device_features.geometry_shader == vk::FALSE {
+        reasons.push(UnsuitableReason::NoGeometryShader);
+    }
+
+    let indices = find_queue_families(instance, surface, khr_extension, device);
+    if indices.graphics_family.is_none() {
+        reasons.push(UnsuitableReason::NoGraphicsQueue);
+    }
+
+    if indices.present_family.is_none() {
+        reasons.push(UnsuitableReason::NoPresentQueue);
+    }
 
-    let mut swapchain_adequate = false;
-    if extensions_supported {
-        let swapchain_details = query_swapchain_support(&khr_extension, surface, device);
-        swapchain_adequate = !swapchain_details.formats.is_empty() && !swapchain_details.presentModes.is_empty();
+    // The swapchain support can only be queried if the swapchain extension is supported.
+    // Other configured extensions have nothing to do with it, so a device missing one of those still gets its swapchain checked.
+    let missing_extensions = check_device_extension_support(instance, device, required_extensions)?;
+    if !missing_extensions.iter().any(|missing_extension| missing_extension == "VK_KHR_swapchain") {
+        let swapchain_details = query_swapchain_support(khr_extension, surface, device)?;
+        if swapchain_details.formats.is_empty() || swapchain_details.present_modes.is_empty() {
+            reasons.push(UnsuitableReason::InadequateSwapchain);
+        }
     }
 
-    let device_name = CStr::from_ptr(device_properties.device_name.as_ptr());
-    println!("Checking physical device: {}", device_name.to_str().expect("Failed to convert CStr to string!"));
-    
-    let selection_criteria = 
-        (device_properties.device_type == vk::PhysicalDeviceType::DISCRETE_GPU && device_features.geometry_shader > 0) 
-        && (find_queue_families(instance, surface, khr_extension, device).is_complete())
-        && extensions_supported
-        && swapchain_adequate;
+    if !missing_extensions.is_empty() {
+        reasons.push(UnsuitableReason::MissingExtensions(missing_extensions));
+    }
+
+    let missing_features = get_missing_features(required_features, &device_features);
+    if !missing_features.is_empty() {
+        reasons.push(UnsuitableReason::MissingFeatures(missing_features));
+    }
 
-    if selection_criteria {
-        println!("Selected physical device: {}", device_name.to_str().expect("Failed to convert CStr to string!"));
+    if reasons.is_empty() {
+        debug!("Physical device is suitable: {}", device_name);
+    } else {
+        debug!("Physical device {} isn't suitable: {}", device_name, join_reasons(&reasons));
     }
 
-    selection_criteria
+    Ok(reasons)
+}
+
+// Lists the names of the required features that aren't in the supported features.
+fn get_missing_features(required_features: &RequiredFeatures, supported_features: &vk::PhysicalDeviceFeatures) -> Vec<&'static str> {
+    let features = [
+        ("sampler_anisotropy", required_features.sampler_anisotropy, supported_features.sampler_anisotropy),
+        ("fill_mode_non_solid", required_features.fill_mode_non_solid, supported_features.fill_mode_non_solid),
+        ("wide_lines", required_features.wide_lines, supported_features.wide_lines),
+    ];
+
+    features
+        .iter()
+        .filter(|(_, is_required, is_supported)| *is_required && *is_supported == vk::FALSE)
+        .map(|(feature_name, _, _)| *feature_name)
+        .collect()
 }
 
-unsafe fn check_device_extension_support(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> bool {
+// Lists the required features that none of the physical devices support.
+unsafe fn get_features_missing_on_all_devices(instance: &ash::Instance, physical_devices: &[vk::PhysicalDevice], required_features: &RequiredFeatures) -> Vec<&'static str> {
+    // Start out with every required feature, and keep only the ones that are also missing on each device.
+    let mut missing_on_all_devices = get_missing_features(required_features, &vk::PhysicalDeviceFeatures::default());
+
+    for &physical_device in physical_devices {
+        let missing_features = get_missing_features(required_features, &instance.get_physical_device_features(physical_device));
+        missing_on_all_devices.retain(|feature_name| missing_features.contains(feature_name));
+    }
+
+    missing_on_all_devices
+}
+
+fn get_enabled_features(required_features: &RequiredFeatures) -> vk::PhysicalDeviceFeatures {
+    vk::PhysicalDeviceFeatures {
+        sampler_anisotropy: required_features.sampler_anisotropy as vk::Bool32,
+        fill_mode_non_solid: required_features.fill_mode_non_solid as vk::Bool32,
+        wide_lines: required_features.wide_lines as vk::Bool32,
+        ..Default::default()
+    }
+}
+
+// Returns the required device extensions that the physical device doesn't support.
+unsafe fn check_device_extension_support(instance: &ash::Instance, physical_device: vk::PhysicalDevice, required_extensions: &[String]) -> Result<Vec<String>, RendererError> {
     // Not all graphics cards are capable of presenting images directly to a screen.
     // In order to get support for presenting images to the screen, we need to enable the VK_KHR_swapchain extension.
     // This extension indicates whether the device is capable of creating a swapchain.
     // So, we need to query our device for support for this extension.
-    let available_device_extensions = instance.enumerate_device_extension_properties(physical_device).unwrap();
+    let available_device_extensions = instance.enumerate_device_extension_properties(physical_device).map_err(RendererError::DeviceExtensionQuery)?;
 
-    let mut required_extensions = REQUIRED_EXTENSIONS.clone();
+    let available_extension_names: HashSet<String> = available_device_extensions
+        .iter()
+        .map(|available_extension| CStr::from_ptr(available_extension.extension_name.as_ptr()).to_string_lossy().into_owned())
+        .collect();
 
-    for available_extension in available_device_extensions {
-        let extension_name = CStr::from_ptr(available_extension.extension_name.as_ptr()).to_str().unwrap();
-        required_extensions.remove(extension_name);
-    }
+    Ok(required_extensions
+        .iter()
+        .filter(|required_extension| !available_extension_names.contains(required_extension.as_str()))
+        .cloned()
+        .collect())
+}
 
-    required_extensions.is_empty()
+unsafe fn get_available_instance_extensions(entry: &Entry) -> Result<HashSet<String>, RendererError> {
+    let available_instance_extensions = entry
+        .enumerate_instance_extension_properties(None)
+        .map_err(RendererError::Instance)?;
+
+    Ok(available_instance_extensions
+        .iter()
+        .map(|available_extension| CStr::from_ptr(available_extension.extension_name.as_ptr()).to_string_lossy().into_owned())
+        .collect())
 }
 
-unsafe fn build_extensions() -> Vec<String> {
+unsafe fn build_extensions(validation_enabled: bool) -> Vec<String> {
     let mut required_extensions: Vec<String> = vec!();
 
     // Get required GLFW extensions
@@ -560,31 +1126,77 @@ unsafe fn build_extensions() -> Vec<String> {
     }
 
     // VK_EXT_debug_utils is a required extension when setting up callback functionality
-    required_extensions.push(String::from("VK_EXT_debug_utils"));
+    if validation_enabled {
+        required_extensions.push(String::from("VK_EXT_debug_utils"));
+    }
 
     required_extensions
 }
 
-unsafe fn populate_debug_messenger_create_info() -> vk::DebugUtilsMessengerCreateInfoEXT {
+// The callback gets a pointer to the validation stats as its user data, so the stats must outlive the messenger.
+unsafe fn populate_debug_messenger_create_info(validation: ValidationLevel, validation_stats: &ValidationStats) -> vk::DebugUtilsMessengerCreateInfoEXT {
     vk::DebugUtilsMessengerCreateInfoEXT {
         s_type: vk::StructureType::DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
-        message_severity: vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+        message_severity: validation.message_severity(),
         message_type: vk::DebugUtilsMessageTypeFlagsEXT::GENERAL | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION,
         pfn_user_callback: Some(vulkan_debug_utils_callback),
-        p_user_data: ptr::null_mut(),
+        p_user_data: validation_stats as *const ValidationStats as *mut c_void,
         ..Default::default()
     }
 }
 
-unsafe fn setup_debug_messenger(debug_utils_ext: &ash::extensions::ext::DebugUtils) -> vk::DebugUtilsMessengerEXT {
+unsafe fn setup_debug_messenger(debug_utils_ext: &ash::extensions::ext::DebugUtils, validation: ValidationLevel, validation_stats: &ValidationStats) -> Result<vk::DebugUtilsMessengerEXT, RendererError> {
     // Fill out the struct describing the kind of debug messenger we'd like
-    let messenger_create_into = populate_debug_messenger_create_info();
+    let messenger_create_into = populate_debug_messenger_create_info(validation, validation_stats);
 
-    let debug_utils_messenger = debug_utils_ext
+    debug_utils_ext
         .create_debug_utils_messenger(&messenger_create_into, None)
-        .expect("Failed to create Debug Utils Messenger");
+        .map_err(RendererError::DebugMessenger)
+}
 
-    debug_utils_messenger
+// Attaches a readable name to a Vulkan object through VK_EXT_debug_utils.
+// Validation layer messages (and tools such as RenderDoc) will then use this name when referring to the object.
+// The object type is taken from the handle type itself, so it can't mismatch the handle.
+// Names are only useful while debugging, so this does nothing in release builds.
+unsafe fn set_debug_name<T: Handle>(debug_utils_ext: &ash::extensions::ext::DebugUtils, device: &ash::Device, object: T, name: &str) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+
+    // The name is copied by the implementation, so it only has to outlive the call below.
+    let object_name = ffi_string(name);
+
+    let name_info = vk::DebugUtilsObjectNameInfoEXT {
+        s_type: vk::StructureType::DEBUG_UTILS_OBJECT_NAME_INFO_EXT,
+        object_type: T::TYPE,
+        object_handle: object.as_raw(),
+        p_object_name: object_name.as_ptr(),
+        ..Default::default()
+    };
+
+    if let Err(err) = debug_utils_ext.set_debug_utils_object_name(device.handle(), &name_info) {
+        warn!("Failed to set debug name \"{}\": {}", name, err);
+    }
+}
+
+// Waits until glfwGetTime() reaches the given deadline, in seconds.
+// Sleeping alone is too imprecise for frame pacing, since the OS scheduler can easily oversleep by a millisecond or more.
+// So I sleep until shortly before the deadline, and then spin for the remaining time.
+unsafe fn wait_until(deadline: f64) {
+    const SPIN_THRESHOLD: f64 = 0.002;
+
+    loop {
+        let remaining = deadline - glfwGetTime();
+        if remaining <= 0.0 {
+            break;
+        }
+
+        if remaining > SPIN_THRESHOLD {
+            std::thread::sleep(Duration::from_secs_f64(remaining - SPIN_THRESHOLD));
+        } else {
+            std::hint::spin_loop();
+        }
+    }
 }
 
 /*
@@ -597,11 +1209,17 @@ fn ffi_string(str: &str) -> CString {
     CString::new(str).expect(&error_message)
 }
 
+// GLFW owns the description string, and it stays valid until the next GLFW error, so it's only borrowed here and copied.
+// When no error is pending, GLFW hands back NULL instead.
 unsafe fn get_latest_glfw_error_description() -> String {
-    let mut error_description_raw: *const i8 = ptr::null_mut();
+    let mut error_description_raw: *const i8 = ptr::null();
     glfwGetError(&mut error_description_raw);
-    let error_description = CString::from_raw(error_description_raw as *mut i8);
-    error_description.into_string().expect("Failed to convert GLFW error description into String type")
+
+    if error_description_raw.is_null() {
+        return String::from("No GLFW error description available");
+    }
+
+    CStr::from_ptr(error_description_raw).to_string_lossy().into_owned()
 }
 
 // Callback function used by Debug Utils extension.
@@ -612,14 +1230,6 @@ unsafe extern "system" fn vulkan_debug_utils_callback(
     p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
     p_user_data: *mut c_void) -> vk::Bool32 {
 
-        let severity = match message_severity {
-            vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => "[Verbose]",
-            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => "[Warning]",
-            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => "[ERROR]",
-            vk::DebugUtilsMessageSeverityFlagsEXT::INFO => "[INFO]",
-            _ => "[Unknown]"
-        };
-
         let types = match message_type {
             vk::DebugUtilsMessageTypeFlagsEXT::GENERAL => "[General]",
             vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE => "[Performance]",
@@ -629,10 +1239,76 @@ unsafe extern "system" fn vulkan_debug_utils_callback(
 
         let message = CStr::from_ptr((*p_callback_data).p_message);
 
-        println!("[Debug]{}{}{:?}", severity, types, message);
+        if !p_user_data.is_null() {
+            (*(p_user_data as *const ValidationStats)).record(message_severity, message_type);
+        }
+
+        // The message severity maps directly onto a log level.
+        // VERBOSE is reported at debug level rather than trace, so "--validation=verbose" shows it without also needing RUST_LOG.
+        match message_severity {
+            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => error!("{}{:?}", types, message),
+            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => warn!("{}{:?}", types, message),
+            vk::DebugUtilsMessageSeverityFlagsEXT::INFO => info!("{}{:?}", types, message),
+            _ => debug!("{}{:?}", types, message)
+        };
 
         // The callback returns a boolean that indicates if the Vulkan call that triggered the validation layer message should
         // be aborted. If the callback returns true, the call is aborted.
         // This is normally used used to test the validation layers themselves, so you should always return VK_FALSE.
         vk::FALSE
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(current_extent: vk::Extent2D, min_image_extent: vk::Extent2D, max_image_extent: vk::Extent2D) -> vk::SurfaceCapabilitiesKHR {
+        vk::SurfaceCapabilitiesKHR {
+            current_extent,
+            min_image_extent,
+            max_image_extent,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn choose_composite_alpha_falls_back_to_a_supported_mode() {
+        let opaque_or_premultiplied = vk::CompositeAlphaFlagsKHR::OPAQUE | vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED;
+        assert_eq!(choose_composite_alpha(opaque_or_premultiplied, true), vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED);
+        assert_eq!(choose_composite_alpha(opaque_or_premultiplied, false), vk::CompositeAlphaFlagsKHR::OPAQUE);
+
+        // A surface without OPAQUE, transparent or not, gets whatever it does support.
+        assert_eq!(choose_composite_alpha(vk::CompositeAlphaFlagsKHR::INHERIT, false), vk::CompositeAlphaFlagsKHR::INHERIT);
+        assert_eq!(choose_composite_alpha(vk::CompositeAlphaFlagsKHR::INHERIT, true), vk::CompositeAlphaFlagsKHR::INHERIT);
+    }
+
+    #[test]
+    fn compute_extent_uses_current_extent_when_set() {
+        let current_extent = vk::Extent2D { width: 800, height: 600 };
+        let capabilities = capabilities(current_extent, vk::Extent2D { width: 1, height: 1 }, vk::Extent2D { width: 4096, height: 4096 });
+
+        assert_eq!(compute_extent((1024, 768), capabilities), current_extent);
+    }
+
+    #[test]
+    fn compute_extent_passes_framebuffer_size_through_within_bounds() {
+        let capabilities = capabilities(
+            vk::Extent2D { width: u32::MAX, height: u32::MAX },
+            vk::Extent2D { width: 1, height: 1 },
+            vk::Extent2D { width: 4096, height: 4096 });
+
+        assert_eq!(compute_extent((1024, 768), capabilities), vk::Extent2D { width: 1024, height: 768 });
+    }
+
+    #[test]
+    fn compute_extent_clamps_framebuffer_size_to_bounds() {
+        let capabilities = capabilities(
+            vk::Extent2D { width: u32::MAX, height: u32::MAX },
+            vk::Extent2D { width: 200, height: 100 },
+            vk::Extent2D { width: 1920, height: 1080 });
+
+        assert_eq!(compute_extent((100, 50), capabilities), vk::Extent2D { width: 200, height: 100 });
+        assert_eq!(compute_extent((4000, 3000), capabilities), vk::Extent2D { width: 1920, height: 1080 });
+        assert_eq!(compute_extent((100, 3000), capabilities), vk::Extent2D { width: 200, height: 1080 });
+    }
+}
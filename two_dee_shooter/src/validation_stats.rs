@@ -0,0 +1,92 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use ash::vk;
+
+// Counts the messages reported by the debug messenger over the whole session, so a summary can be printed at exit.
+// The debug callback receives a pointer to this through "p_user_data".
+// The callback may be called from any thread the driver likes, hence the atomics.
+// Since validation messages can be reported all the way up to and including vkDestroyInstance, this has to outlive the instance.
+#[derive(Default)]
+pub struct ValidationStats {
+    errors: AtomicU32,
+    warnings: AtomicU32,
+    infos: AtomicU32,
+    verbose: AtomicU32,
+    general: AtomicU32,
+    validation: AtomicU32,
+    performance: AtomicU32
+}
+
+impl ValidationStats {
+    pub fn record(&self, message_severity: vk::DebugUtilsMessageSeverityFlagsEXT, message_type: vk::DebugUtilsMessageTypeFlagsEXT) {
+        let severity_counter = match message_severity {
+            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => &self.errors,
+            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => &self.warnings,
+            vk::DebugUtilsMessageSeverityFlagsEXT::INFO => &self.infos,
+            _ => &self.verbose
+        };
+        severity_counter.fetch_add(1, Ordering::Relaxed);
+
+        // A single message can have several types.
+        if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::GENERAL) {
+            self.general.fetch_add(1, Ordering::Relaxed);
+        }
+        if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION) {
+            self.validation.fetch_add(1, Ordering::Relaxed);
+        }
+        if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE) {
+            self.performance.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn error_count(&self) -> u32 {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Display for ValidationStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Validation summary: {} errors, {} warnings, {} info, {} verbose ({} general, {} validation, {} perf)",
+            self.errors.load(Ordering::Relaxed),
+            self.warnings.load(Ordering::Relaxed),
+            self.infos.load(Ordering::Relaxed),
+            self.verbose.load(Ordering::Relaxed),
+            self.general.load(Ordering::Relaxed),
+            self.validation.load(Ordering::Relaxed),
+            self.performance.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_counts_per_severity_and_type() {
+        let stats = ValidationStats::default();
+
+        stats.record(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR, vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION);
+        stats.record(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING, vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE);
+        stats.record(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING, vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE);
+        stats.record(vk::DebugUtilsMessageSeverityFlagsEXT::INFO, vk::DebugUtilsMessageTypeFlagsEXT::GENERAL);
+        stats.record(vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE, vk::DebugUtilsMessageTypeFlagsEXT::GENERAL);
+
+        assert_eq!(stats.error_count(), 1);
+        assert_eq!(
+            stats.to_string(),
+            "Validation summary: 1 errors, 2 warnings, 1 info, 1 verbose (2 general, 2 validation, 2 perf)");
+    }
+
+    #[test]
+    fn error_count_ignores_warnings() {
+        let stats = ValidationStats::default();
+
+        stats.record(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING, vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION);
+        stats.record(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING, vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE);
+
+        assert_eq!(stats.error_count(), 0);
+    }
+}
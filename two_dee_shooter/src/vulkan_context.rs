@@ -0,0 +1,201 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+use ash::extensions::ext::DebugUtils;
+use ash::extensions::khr::{Surface, Swapchain};
+use ash::vk;
+
+use beagle_glfw::*;
+
+use crate::gpu_info::GpuInfo;
+use crate::validation_stats::ValidationStats;
+
+// Owns every GLFW and Vulkan object created during initialization.
+// Initialization can fail part way through, so each resource is an Option that is only filled in once it has actually been created.
+// Each resource is wrapped in a guard that destroys it when dropped, and a None is simply skipped.
+// Rust drops struct fields in declaration order, so the order of the fields below IS the teardown order.
+// Objects must be destroyed before whatever they were created from, so anything created later has to be declared earlier.
+#[derive(Default)]
+pub struct VulkanContext {
+    pub swapchain: Option<SwapchainGuard>,
+    pub device: Option<DeviceGuard>,
+    // Destroying the debug messenger must be done before the Vulkan instance is destroyed.
+    pub debug_messenger: Option<DebugMessengerGuard>,
+    pub surface: Option<SurfaceGuard>,
+    pub instance: Option<InstanceGuard>,
+    // The debug callback points into this through "p_user_data", including while the instance is being destroyed.
+    pub validation_stats: Option<ValidationStatsGuard>,
+    pub entry: Option<ash::Entry>,
+    pub window: Option<WindowGuard>,
+    pub glfw: Option<GlfwGuard>,
+    // Not a resource, so its position doesn't matter. It's kept here because it's captured once after device selection
+    // and stays valid for as long as the context does.
+    pub gpu_info: Option<GpuInfo>
+}
+
+// Before terminating your application, you should terminate the GLFW library if it has been initialized.
+// If you don't global system settings changed by GLFW might not be restored properly.
+pub struct GlfwGuard;
+
+impl Drop for GlfwGuard {
+    fn drop(&mut self) {
+        unsafe {
+            glfwTerminate();
+        }
+    }
+}
+
+pub struct WindowGuard(pub *mut GLFWwindow);
+
+impl Drop for WindowGuard {
+    fn drop(&mut self) {
+        unsafe {
+            glfwDestroyWindow(self.0);
+        }
+    }
+}
+
+// Prints the validation summary once the instance is gone, so messages reported while destroying it are counted too.
+// The summary is only printed when validation was actually running, otherwise the counts are meaningless.
+pub struct ValidationStatsGuard {
+    pub stats: Arc<ValidationStats>,
+    pub report: bool
+}
+
+impl Drop for ValidationStatsGuard {
+    fn drop(&mut self) {
+        if !self.report {
+            return;
+        }
+
+        if self.stats.error_count() > 0 {
+            error!("{}", self.stats);
+        } else {
+            info!("{}", self.stats);
+        }
+    }
+}
+
+// Safety: vkDestroyInstance requires every object created from the instance to be destroyed already.
+// That's the device, the debug messenger and the surface, which are all declared before the instance in VulkanContext.
+// If initialization failed before any of them were created, their fields are None and there is nothing to destroy.
+// Destruction never unwraps anything, so cleanup can't panic on top of the original error while unwinding.
+pub struct InstanceGuard(pub ash::Instance);
+
+impl Deref for InstanceGuard {
+    type Target = ash::Instance;
+
+    fn deref(&self) -> &ash::Instance {
+        &self.0
+    }
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        unsafe {
+            self.0.destroy_instance(None);
+        }
+    }
+}
+
+// TODO: Does Ash handle any of these calls in Drop implementations of the structs??
+pub struct DebugMessengerGuard {
+    pub loader: DebugUtils,
+    pub messenger: vk::DebugUtilsMessengerEXT
+}
+
+impl Drop for DebugMessengerGuard {
+    fn drop(&mut self) {
+        unsafe {
+            self.loader.destroy_debug_utils_messenger(self.messenger, None);
+        }
+    }
+}
+
+pub struct SurfaceGuard {
+    pub extension: Surface,
+    pub surface: vk::SurfaceKHR
+}
+
+impl Drop for SurfaceGuard {
+    fn drop(&mut self) {
+        unsafe {
+            self.extension.destroy_surface(self.surface, None);
+        }
+    }
+}
+
+pub struct DeviceGuard(pub ash::Device);
+
+impl Deref for DeviceGuard {
+    type Target = ash::Device;
+
+    fn deref(&self) -> &ash::Device {
+        &self.0
+    }
+}
+
+impl Drop for DeviceGuard {
+    fn drop(&mut self) {
+        unsafe {
+            self.0.destroy_device(None);
+        }
+    }
+}
+
+pub struct SwapchainGuard {
+    pub extension: Swapchain,
+    pub swapchain: vk::SwapchainKHR
+}
+
+impl Drop for SwapchainGuard {
+    fn drop(&mut self) {
+        unsafe {
+            self.extension.destroy_swapchain(self.swapchain, None);
+        }
+    }
+}
+
+// The full stack, from GLFW up to the swapchain, needs a window system and a Vulkan device, so that test is ignored by default.
+// Run it with "cargo test -- --ignored" on a machine that has both.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::{Config, ValidationLevel};
+
+    // Builds the whole stack with validation enabled, runs a single frame and tears it all down again.
+    // Any object destroyed out of order, or not at all, is reported by the validation layers, so the session must end without errors.
+    #[test]
+    #[ignore = "needs a window system, a Vulkan device and the validation layers"]
+    fn full_stack_tears_down_without_validation_errors() {
+        let config = Config {
+            validation: ValidationLevel::On,
+            frame_limit: Some(1),
+            ..Config::default()
+        };
+
+        let validation_errors = crate::run(&config).unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(validation_errors, 0);
+    }
+
+    #[test]
+    fn empty_context_drops_without_panicking() {
+        drop(VulkanContext::default());
+    }
+
+    // When instance creation fails, the validation stats already exist and validation was requested, but nothing else was created.
+    // Dropping that must skip every missing resource and still print the summary, without a secondary panic.
+    #[test]
+    fn context_after_failed_instance_creation_drops_without_panicking() {
+        let context = VulkanContext {
+            validation_stats: Some(ValidationStatsGuard {
+                stats: Arc::new(ValidationStats::default()),
+                report: true
+            }),
+            ..Default::default()
+        };
+
+        drop(context);
+    }
+}